@@ -0,0 +1,109 @@
+#!/usr/bin/env python3
+"""Generates data/languages.tsv and data/kaikki_sections.tsv.
+
+Every language with an ISO 639-3 code gets a row, so that kaikki.org
+sections of rarely looked up languages are found as well. Names come from
+the ISO 639-3 tables of the iso-codes package (Debian: `apt install
+iso-codes`), their translations give the section names of the non-English
+editions. Rows already in the files are kept as they are: they carry the
+names and codes Wiktionary uses where they differ from ISO 639-3, e.g.
+"Greek" instead of "Modern Greek (1453-)" or "sh" for Serbo-Croatian.
+
+Usage, from backend/: scripts/generate_language_data.py
+"""
+
+import gettext
+import json
+import re
+from pathlib import Path
+
+ISO_CODES = Path("/usr/share/iso-codes/json/iso_639-3.json")
+LOCALES = Path("/usr/share/locale")
+DATA = Path(__file__).resolve().parent.parent / "data"
+
+LANGUAGES_HEADER = (
+    "# Generated by scripts/generate_language_data.py, rows present before are kept.\n"
+    "# ISO 639-3\tWiktionary code\tEnglish name (= kaikki.org section of the English Wiktionary)\n"
+)
+SECTIONS_HEADER = (
+    "# Generated by scripts/generate_language_data.py, rows present before are kept.\n"
+    "# Language section names of the non-English editions, in the edition language.\n"
+    "# The English edition uses the English names from languages.tsv.\n"
+    "# Edition (ISO 639-3)\tLanguage (ISO 639-3)\tSection\n"
+)
+
+
+def rows(path):
+    with open(path, encoding="utf-8") as f:
+        for line in f:
+            line = line.rstrip("\n")
+            if line.strip() and not line.startswith("#"):
+                yield [cell.strip() for cell in line.split("\t")]
+
+
+def plain_name(name):
+    """"Malay (macrolanguage)" -> "Malay", "Old English (ca. 450-1100)" -> "Old English"."""
+    return re.sub(r"\s*\([^)]*\)\s*$", "", name).strip()
+
+
+def iso_languages():
+    """Living, extinct, ancient, historical and constructed languages."""
+    with open(ISO_CODES, encoding="utf-8") as f:
+        entries = json.load(f)["639-3"]
+    return [e for e in entries if e["type"] != "S" and e["scope"] != "S"]
+
+
+def generate_languages(entries):
+    curated = {iso3: (code, name) for iso3, code, name in rows(DATA / "languages.tsv")}
+    languages = dict(curated)
+    for e in entries:
+        if e["alpha_3"] not in languages:
+            code = e.get("alpha_2", e["alpha_3"])
+            languages[e["alpha_3"]] = (code, plain_name(e["name"]))
+    # Rows present before come first and keep their order, so that the same
+    # languages win lookups by shared Wiktionary codes.
+    generated = sorted(
+        (item for item in languages.items() if item[0] not in curated),
+        key=lambda item: (item[1][1].casefold(), item[0]),
+    )
+    ordered = list(curated.items()) + generated
+    with open(DATA / "languages.tsv", "w", encoding="utf-8") as f:
+        f.write(LANGUAGES_HEADER)
+        for iso3, (code, name) in ordered:
+            f.write(f"{iso3}\t{code}\t{name}\n")
+    return languages
+
+
+def generate_sections(entries, languages):
+    editions = [edition for edition, _ in rows(DATA / "kaikki_editions.tsv")]
+    curated = {(edition, lang): section for edition, lang, section in rows(DATA / "kaikki_sections.tsv")}
+    iso1 = {e["alpha_3"]: e.get("alpha_2") for e in entries}
+    with open(DATA / "kaikki_sections.tsv", "w", encoding="utf-8") as f:
+        f.write(SECTIONS_HEADER)
+        for edition in editions:
+            if edition == "eng":
+                continue
+            locale = iso1.get(edition) or edition
+            translation = gettext.translation("iso_639-3", LOCALES, languages=[locale])
+            kept = {lang: s for (e, lang), s in curated.items() if e == edition}
+            sections = {}
+            for entry in entries:
+                lang = entry["alpha_3"]
+                translated = translation.gettext(entry["name"])
+                if lang in kept or lang not in languages or translated == entry["name"]:
+                    continue
+                name = plain_name(translated)
+                sections[lang] = name[:1].upper() + name[1:]
+            generated = sorted(sections.items(), key=lambda s: (s[1].casefold(), s[0]))
+            for lang, section in list(kept.items()) + generated:
+                f.write(f"{edition}\t{lang}\t{section}\n")
+
+
+def main():
+    entries = iso_languages()
+    languages = generate_languages(entries)
+    generate_sections(entries, languages)
+
+
+if __name__ == "__main__":
+    main()
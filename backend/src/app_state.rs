@@ -1,24 +1,81 @@
+use crate::kaikki::{kaikki_proxy, kaikki_store::KaikkiStore};
+use crate::llm::lexical_cache::LexicalCache;
+use crate::llm::llm_provider::{LlmConfig, LlmProvider};
+use crate::llm::usage_ledger::UsageLedger;
+use crate::panlex::db_check;
+use crate::panlex::sqlite::Precomputed;
+use crate::tatoeba::{tatoeba_proxy, tatoeba_store::TatoebaStore};
+use crate::upstream::circuit_breaker::UpstreamBreakers;
+use crate::upstream::upstream_proxy::Upstream;
+use crate::wortschatz_leipzig::{leipzig_client::CorpusCatalog, wortschatz_leipzig_proxy};
+use axum::http::StatusCode;
 use reqwest::Client;
 use sqlx::SqlitePool;
+use std::sync::Arc;
 use std::time::Duration;
 
+/// The PanLex DB, with what was precomputed in it.
+#[derive(Clone)]
+pub struct PanlexDb {
+    pub pool: SqlitePool,
+    pub precomputed: Precomputed,
+}
+
+/// A local copy of an upstream's data.
+#[derive(Clone)]
+pub struct LocalDb<T> {
+    pub store: T,
+    /// Whether entries missing in `store` are requested from the upstream.
+    pub live_fallback: bool,
+}
+
 #[derive(Clone)]
 pub struct AppState {
     http_client: Client,
-    chatgpt_key: String,
-    panlex_sqlite_pool: SqlitePool,
+    llm: Arc<dyn LlmProvider>,
+    llm_cache: Option<LexicalCache>,
+    llm_ledger: Option<UsageLedger>,
+    panlex: Option<PanlexDb>,
+    kaikki: Option<LocalDb<KaikkiStore>>,
+    tatoeba: Option<LocalDb<TatoebaStore>>,
+    leipzig_corpora: CorpusCatalog,
+    kaikki_upstream: Upstream,
+    tatoeba_upstream: Upstream,
+    leipzig_upstream: Upstream,
+    breakers: UpstreamBreakers,
+    admin_token: Option<String>,
 }
 
 impl AppState {
     pub fn new(
-        chatgpt_key: String,
-        panlex_sqlite_pool: SqlitePool,
+        llm_config: LlmConfig,
+        llm_cache: Option<LexicalCache>,
+        llm_ledger: Option<UsageLedger>,
+        panlex: Option<PanlexDb>,
+        kaikki: Option<LocalDb<KaikkiStore>>,
+        tatoeba: Option<LocalDb<TatoebaStore>>,
+        admin_token: Option<String>,
     ) -> Result<Self, reqwest::Error> {
         let http_client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+        let breakers = UpstreamBreakers::default();
+        let llm = llm_config.build(&http_client, breakers.openai.clone());
         Ok(Self {
             http_client,
-            chatgpt_key,
-            panlex_sqlite_pool,
+            llm,
+            llm_cache,
+            llm_ledger,
+            panlex,
+            kaikki,
+            tatoeba,
+            leipzig_corpora: CorpusCatalog::default(),
+            kaikki_upstream: Upstream::new(kaikki_proxy::UPSTREAM, breakers.kaikki.clone()),
+            tatoeba_upstream: Upstream::new(tatoeba_proxy::UPSTREAM, breakers.tatoeba.clone()),
+            leipzig_upstream: Upstream::new(
+                wortschatz_leipzig_proxy::UPSTREAM,
+                breakers.leipzig.clone(),
+            ),
+            breakers,
+            admin_token,
         })
     }
 
@@ -26,11 +83,71 @@ impl AppState {
         &self.http_client
     }
 
-    pub fn chatgpt_key(&self) -> &str {
-        &self.chatgpt_key
+    pub fn llm(&self) -> &dyn LlmProvider {
+        self.llm.as_ref()
+    }
+
+    pub fn llm_cache(&self) -> Option<&LexicalCache> {
+        self.llm_cache.as_ref()
+    }
+
+    pub fn llm_ledger(&self) -> Option<&UsageLedger> {
+        self.llm_ledger.as_ref()
+    }
+
+    /// `Err` with [`db_check::DISABLED_STATUS`] when the server runs without
+    /// PanLex because its DB was unusable.
+    pub fn panlex(&self) -> Result<&PanlexDb, (StatusCode, String)> {
+        self.panlex
+            .as_ref()
+            .ok_or_else(|| (db_check::DISABLED_STATUS, "PanLex is disabled".to_string()))
+    }
+
+    /// Local Wiktextract dump; `None` makes Kaikki lookups go to kaikki.org.
+    pub fn kaikki(&self) -> Option<&LocalDb<KaikkiStore>> {
+        self.kaikki.as_ref()
+    }
+
+    pub fn kaikki_store(&self) -> Option<&KaikkiStore> {
+        self.kaikki.as_ref().map(|db| &db.store)
+    }
+
+    /// Whether words missing in the local Kaikki DB are fetched from kaikki.org.
+    pub fn kaikki_live_fallback(&self) -> bool {
+        self.kaikki.as_ref().is_some_and(|db| db.live_fallback)
+    }
+
+    /// Local Tatoeba corpus; `None` makes example searches go to tatoeba.org.
+    pub fn tatoeba(&self) -> Option<&LocalDb<TatoebaStore>> {
+        self.tatoeba.as_ref()
+    }
+
+    pub fn leipzig_corpora(&self) -> &CorpusCatalog {
+        &self.leipzig_corpora
+    }
+
+    /// kaikki.org as seen by the `/kaikki` proxy.
+    pub fn kaikki_upstream(&self) -> &Upstream {
+        &self.kaikki_upstream
+    }
+
+    /// tatoeba.org as seen by the `/tatoeba` proxy.
+    pub fn tatoeba_upstream(&self) -> &Upstream {
+        &self.tatoeba_upstream
+    }
+
+    /// Wortschatz Leipzig as seen by the `/ws/sentences` proxy.
+    pub fn leipzig_upstream(&self) -> &Upstream {
+        &self.leipzig_upstream
+    }
+
+    /// Circuit breakers of the upstreams, shared by all their callers.
+    pub fn breakers(&self) -> &UpstreamBreakers {
+        &self.breakers
     }
 
-    pub fn panlex_sqlite_pool(&self) -> &SqlitePool {
-        &self.panlex_sqlite_pool
+    /// `None` disables all admin operations.
+    pub fn admin_token(&self) -> Option<&str> {
+        self.admin_token.as_deref()
     }
 }
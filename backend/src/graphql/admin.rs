@@ -0,0 +1,11 @@
+use crate::app_state::AppState;
+use async_graphql::{Error, ErrorExtensions};
+
+/// Admin operations take the admin token as an argument; they are disabled
+/// altogether unless the server was started with `--admin-token`.
+pub fn check_admin_token(state: &AppState, admin_token: &str) -> async_graphql::Result<()> {
+    match state.admin_token() {
+        Some(expected) if expected == admin_token => Ok(()),
+        _ => Err(Error::new("invalid admin token").extend_with(|_, e| e.set("code", "FORBIDDEN"))),
+    }
+}
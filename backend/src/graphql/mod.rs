@@ -1,2 +1,5 @@
+mod admin;
+pub mod mutation;
 pub mod query;
+pub mod response_extensions;
 pub mod schema;
@@ -0,0 +1,93 @@
+use super::admin::check_admin_token;
+use crate::app_state::AppState;
+use async_graphql::{Context, Error, ErrorExtensions, Object};
+
+pub struct Mutation;
+
+#[Object]
+impl Mutation {
+    /// Admin only: deletes cached LLM answers matching all given filters
+    /// (omitted filters match anything). Returns the number of deleted entries.
+    async fn purge_llm_cache(
+        &self,
+        ctx: &Context<'_>,
+        admin_token: String,
+        query: Option<String>,
+        lang_from_iso3: Option<String>,
+        lang_to_iso3: Option<String>,
+    ) -> async_graphql::Result<u64> {
+        let state = ctx.data::<AppState>()?;
+        check_admin_token(state, &admin_token)?;
+        let Some(cache) = state.llm_cache() else {
+            return Ok(0);
+        };
+        cache
+            .purge(
+                query.as_deref().map(str::trim),
+                lang_from_iso3.as_deref(),
+                lang_to_iso3.as_deref(),
+            )
+            .await
+            .map_err(|e| {
+                Error::new("LLM cache error").extend_with(|_, ext| {
+                    ext.set("code", "LLM_CACHE");
+                    ext.set("message", e.to_string());
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app_state::{AppState, PanlexDb};
+    use crate::graphql::schema::build_schema;
+    use crate::llm::lexical_cache::LexicalCache;
+    use crate::llm::llm_provider::LlmConfig;
+    use crate::panlex::sqlite::Precomputed;
+    use serde_json::json;
+    use sqlx::SqlitePool;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::time::Duration;
+
+    async fn state_with_cache() -> AppState {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let cache = LexicalCache::new(pool, Duration::from_secs(60))
+            .await
+            .unwrap();
+        cache.put("Hund", "deu", "eng", 1, &"cached").await;
+        let panlex = PanlexDb {
+            pool: SqlitePool::connect_lazy("sqlite::memory:").unwrap(),
+            precomputed: Precomputed::default(),
+        };
+        AppState::new(
+            LlmConfig::Fake,
+            Some(cache),
+            None,
+            Some(panlex),
+            None,
+            None,
+            Some("secret".into()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn purge_requires_admin_token() {
+        let schema = build_schema(state_with_cache().await);
+        let res = schema
+            .execute(r#"mutation { purgeLlmCache(adminToken: "wrong") }"#)
+            .await;
+        assert_eq!(res.errors.len(), 1);
+        assert_eq!(res.errors[0].message, "invalid admin token");
+
+        let res = schema
+            .execute(r#"mutation { purgeLlmCache(adminToken: "secret", query: " Hund ") }"#)
+            .await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        assert_eq!(res.data.into_json().unwrap(), json!({ "purgeLlmCache": 1 }));
+    }
+}
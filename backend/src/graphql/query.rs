@@ -1,8 +1,22 @@
+use super::admin::check_admin_token;
+use super::response_extensions::ResponseExtensions;
 use crate::app_state::AppState;
-use crate::llm::chatgpt_lexical_items;
-use crate::model::LexicalItemDetail;
-use crate::panlex::panlex_lexical_items;
+use crate::kaikki::kaikki_lexical_items;
+use crate::llm::chatgpt_lexical_items::{self, LlmCallStats};
+use crate::lookup::{self, Source};
+use crate::model::{
+    Corpus, LexicalItemDetail, LlmUsageReport, PanlexVariety, lexical_item_detail::Example,
+};
+use crate::normalize;
+use crate::panlex::sqlite::{self as panlex_sqlite, is_lang_selector};
+use crate::panlex::{db_check, expr_index, panlex_lexical_items};
+use crate::tatoeba::tatoeba_client::{self, TatoebaSearch};
+use crate::tatoeba::tatoeba_lexical_items;
+use crate::upstream::source_error::SourceError;
+use crate::wortschatz_leipzig::leipzig_client::DEFAULT_BASE_URL;
+use crate::wortschatz_leipzig::leipzig_lexical_items;
 use async_graphql::{Context, Error, ErrorExtensions, Object};
+use axum::http::StatusCode;
 
 pub struct Query;
 
@@ -15,59 +29,352 @@ impl Query {
         lang_from_iso3: String,
         lang_to_iso3: String,
     ) -> async_graphql::Result<Vec<LexicalItemDetail>> {
-        validate_params(&query, &lang_from_iso3, &lang_to_iso3)?;
+        let query = validate_params(&query, &lang_from_iso3, &lang_to_iso3)?;
         let state = ctx.data::<AppState>()?;
-        chatgpt_lexical_items::request(
-            state.http_client(),
-            state.chatgpt_key(),
+        let result = chatgpt_lexical_items::request(
+            state.llm(),
+            state.llm_cache(),
+            state.llm_ledger(),
             &query,
             &lang_from_iso3,
             &lang_to_iso3,
-            None,
-            None,
         )
         .await
-        .map_err(|(status, msg)| {
-            Error::new("Upstream LLM error").extend_with(|_, e| {
-                e.set("code", "UPSTREAM_LLM");
-                e.set("httpStatus", status.as_u16());
-                e.set("message", msg);
-            })
-        })
+        .map_err(|e| source_error(Source::Chatgpt, "LLM", "Bad LLM request", e))?;
+        report_llm_stats(ctx, result.stats);
+        Ok(result.items)
     }
 
+    /// PanLex translations and synonyms. Languages are ISO 639-3 codes,
+    /// matching all their varieties, or variety UIDs like "srp-001" (see
+    /// `panlexVarieties`). With `pivotLangsIso3`, e.g. ["eng", "spa"],
+    /// translations reached through those languages are added as
+    /// `IndirectTranslations`.
     async fn panlex(
         &self,
         ctx: &Context<'_>,
         query: String,
         lang_from_iso3: String,
         lang_to_iso3: String,
+        #[graphql(default)] pivot_langs_iso3: Vec<String>,
     ) -> async_graphql::Result<Vec<LexicalItemDetail>> {
-        validate_params(&query, &lang_from_iso3, &lang_to_iso3)?;
+        let query = validate_query(&query)?;
+        if !is_lang_selector(&lang_from_iso3) || !is_lang_selector(&lang_to_iso3) {
+            return Err(Error::new("languages must be ISO-3 codes or PanLex UIDs")
+                .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")));
+        }
+        if MAX_PIVOT_LANGS < pivot_langs_iso3.len()
+            || !pivot_langs_iso3.iter().all(|l| is_lang_selector(l))
+        {
+            return Err(Error::new(format!(
+                "pivotLangsIso3 must be at most {MAX_PIVOT_LANGS} ISO-3 codes or PanLex UIDs"
+            ))
+            .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")));
+        }
         let state = ctx.data::<AppState>()?;
+        let panlex = state.panlex().map_err(panlex_error)?;
         panlex_lexical_items::get(
-            state.panlex_sqlite_pool(),
+            &panlex.pool,
+            &panlex.precomputed,
             &query,
             &lang_from_iso3,
             &lang_to_iso3,
+            &pivot_langs_iso3,
+        )
+        .await
+        .map_err(panlex_error)
+    }
+
+    /// PanLex expressions of `langIso3` (an ISO-3 code or PanLex UID)
+    /// starting with `prefix`. Other spellings are matched too once the
+    /// `prepare-panlex` subcommand was run with `--normalize` or
+    /// `--index-expressions`.
+    async fn autocomplete(
+        &self,
+        ctx: &Context<'_>,
+        prefix: String,
+        lang_iso3: String,
+        #[graphql(default = 10)] limit: u32,
+    ) -> async_graphql::Result<Vec<String>> {
+        let prefix = validate_query(&prefix)?;
+        if !is_lang_selector(&lang_iso3) {
+            return Err(Error::new("language must be an ISO-3 code or PanLex UID")
+                .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")));
+        }
+        if limit == 0 || MAX_AUTOCOMPLETE_LIMIT < limit {
+            return Err(Error::new(format!(
+                "limit must be between 1 and {MAX_AUTOCOMPLETE_LIMIT}"
+            ))
+            .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")));
+        }
+        let state = ctx.data::<AppState>()?;
+        let panlex = state.panlex().map_err(panlex_error)?;
+        expr_index::autocomplete(
+            &panlex.pool,
+            &panlex.precomputed,
+            &prefix,
+            &lang_iso3,
+            limit as usize,
+        )
+        .await
+        .map_err(panlex_error)
+    }
+
+    /// PanLex varieties of `langIso3`, e.g. the Cyrillic and Latin Serbian.
+    async fn panlex_varieties(
+        &self,
+        ctx: &Context<'_>,
+        lang_iso3: String,
+    ) -> async_graphql::Result<Vec<PanlexVariety>> {
+        let state = ctx.data::<AppState>()?;
+        let panlex = state.panlex().map_err(panlex_error)?;
+        panlex_sqlite::get_varieties(&panlex.pool, lang_iso3.trim())
+            .await
+            .map_err(panlex_error)
+    }
+
+    /// Wiktionary data of `query`, parsed from its Kaikki (Wiktextract) page.
+    /// `editionIso3` selects the Wiktionary describing the word, e.g. "eng"
+    /// for German words explained in English; by default the word's own
+    /// language edition if kaikki.org publishes it, English otherwise.
+    async fn kaikki(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        lang_iso3: String,
+        edition_iso3: Option<String>,
+    ) -> async_graphql::Result<Vec<LexicalItemDetail>> {
+        let query = validate_params(
+            &query,
+            &lang_iso3,
+            edition_iso3.as_deref().unwrap_or(&lang_iso3),
+        )?;
+        let state = ctx.data::<AppState>()?;
+        kaikki_lexical_items::get(
+            state.http_client(),
+            state.kaikki_upstream(),
+            state.kaikki(),
+            &query,
+            &lang_iso3,
+            edition_iso3.as_deref(),
+            None,
         )
         .await
-        .map_err(|(status, msg)| {
-            Error::new("PanLex SQLite error").extend_with(|_, e| {
-                e.set("code", "PANLEX_SQLITE");
-                e.set("httpStatus", status.as_u16());
-                e.set("message", msg);
+        .map_err(|e| {
+            source_error(
+                Source::Kaikki,
+                "Kaikki",
+                "Unsupported Kaikki language or edition",
+                e,
+            )
+        })
+    }
+
+    /// Tatoeba sentences in `langFromIso3` containing `query`, with their
+    /// translations into `langToIso3`. Served from the local Tatoeba DB
+    /// when one is configured.
+    async fn examples(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        lang_from_iso3: String,
+        lang_to_iso3: String,
+        #[graphql(default = 10)] limit: u32,
+    ) -> async_graphql::Result<Vec<Example>> {
+        let query = validate_params(&query, &lang_from_iso3, &lang_to_iso3)?;
+        if limit == 0 || tatoeba_client::MAX_LIMIT < limit as usize {
+            return Err(Error::new(format!(
+                "limit must be between 1 and {}",
+                tatoeba_client::MAX_LIMIT
+            ))
+            .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")));
+        }
+        let state = ctx.data::<AppState>()?;
+        let search = TatoebaSearch {
+            query: query.trim(),
+            from: &lang_from_iso3,
+            to: &lang_to_iso3,
+            limit: limit as usize,
+        };
+        tatoeba_lexical_items::get(
+            state.http_client(),
+            state.tatoeba_upstream(),
+            state.tatoeba(),
+            &search,
+            None,
+        )
+        .await
+        .map_err(|e| source_error(Source::Tatoeba, "Tatoeba", "Bad Tatoeba request", e))
+    }
+
+    /// Wortschatz Leipzig data of `query` from `corpus`, by default the
+    /// first of `leipzigCorpora(langIso3)`.
+    async fn leipzig(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        lang_iso3: String,
+        corpus: Option<String>,
+    ) -> async_graphql::Result<Vec<LexicalItemDetail>> {
+        let query = validate_params(&query, &lang_iso3, &lang_iso3)?;
+        let state = ctx.data::<AppState>()?;
+        leipzig_lexical_items::get(
+            state.http_client(),
+            state.leipzig_upstream(),
+            state.leipzig_corpora(),
+            &query,
+            &lang_iso3,
+            corpus.as_deref(),
+            None,
+        )
+        .await
+        .map_err(leipzig_error)
+    }
+
+    /// Wortschatz Leipzig corpora of `langIso3`, the default one first.
+    async fn leipzig_corpora(
+        &self,
+        ctx: &Context<'_>,
+        lang_iso3: String,
+    ) -> async_graphql::Result<Vec<Corpus>> {
+        let state = ctx.data::<AppState>()?;
+        state
+            .leipzig_corpora()
+            .corpora_of(
+                state.http_client(),
+                state.leipzig_upstream(),
+                DEFAULT_BASE_URL,
+                lang_iso3.trim(),
+            )
+            .await
+            .map_err(leipzig_error)
+    }
+
+    /// Queries all `sources` (all of them by default) concurrently.
+    /// Failed sources are reported as errors next to the merged data.
+    async fn lookup(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        lang_from_iso3: String,
+        lang_to_iso3: String,
+        sources: Option<Vec<Source>>,
+    ) -> async_graphql::Result<Vec<LexicalItemDetail>> {
+        let query = validate_params(&query, &lang_from_iso3, &lang_to_iso3)?;
+        let state = ctx.data::<AppState>()?;
+        let sources = sources.unwrap_or_else(|| Source::ALL.to_vec());
+        let result = lookup::lookup(state, &query, &lang_from_iso3, &lang_to_iso3, &sources).await;
+
+        for failure in result.failures {
+            let err = Error::new(format!("{} source failed", failure.source.name())).extend_with(
+                |_, e| {
+                    e.set("code", failure.code);
+                    e.set("source", failure.source.name());
+                    e.set("httpStatus", failure.status.as_u16());
+                    e.set("message", failure.message);
+                },
+            );
+            ctx.add_error(err.into_server_error(ctx.item.pos));
+        }
+        if let Some(stats) = result.llm_stats {
+            report_llm_stats(ctx, stats);
+        }
+        Ok(result.items)
+    }
+
+    /// Admin only: LLM token usage and estimated cost over the last `days` days.
+    async fn llm_usage(
+        &self,
+        ctx: &Context<'_>,
+        admin_token: String,
+        #[graphql(default = 30)] days: u32,
+    ) -> async_graphql::Result<LlmUsageReport> {
+        let state = ctx.data::<AppState>()?;
+        check_admin_token(state, &admin_token)?;
+        let Some(ledger) = state.llm_ledger() else {
+            return Err(Error::new("LLM usage ledger is disabled")
+                .extend_with(|_, e| e.set("code", "LLM_LEDGER")));
+        };
+        let to_error = |e: sqlx::Error| {
+            Error::new("LLM usage ledger error").extend_with(|_, ext| {
+                ext.set("code", "LLM_LEDGER");
+                ext.set("message", e.to_string());
             })
+        };
+        Ok(LlmUsageReport {
+            daily: ledger.daily(days).await.map_err(to_error)?,
+            by_language_pair: ledger.by_language_pair(days).await.map_err(to_error)?,
+            spent_today_usd: ledger.spent_today_usd().await.map_err(to_error)?,
+            daily_budget_usd: ledger.daily_budget_usd(),
         })
     }
 }
 
+fn panlex_error((status, msg): (StatusCode, String)) -> Error {
+    let (message, code) = if status == db_check::DISABLED_STATUS {
+        ("PanLex disabled", db_check::DISABLED_CODE)
+    } else {
+        ("PanLex SQLite error", "PANLEX_SQLITE")
+    };
+    Error::new(message).extend_with(|_, e| {
+        e.set("code", code);
+        e.set("httpStatus", status.as_u16());
+        e.set("message", msg);
+    })
+}
+
+fn leipzig_error(e: SourceError) -> Error {
+    source_error(Source::Leipzig, "Leipzig", "Unknown Leipzig corpus", e)
+}
+
+/// GraphQL error of `source`, called `name` in messages, failing with `e`;
+/// `bad_input` describes the requests it refuses.
+fn source_error(source: Source, name: &str, bad_input: &str, e: SourceError) -> Error {
+    let message = match &e {
+        SourceError::BadInput(_) => bad_input.to_string(),
+        SourceError::Unavailable(_) => format!("{name} unavailable"),
+        SourceError::BudgetExceeded(_) => format!("{name} budget exceeded"),
+        SourceError::Upstream(..) | SourceError::Internal(_) => format!("Upstream {name} error"),
+    };
+    Error::new(message).extend_with(|_, ext| {
+        ext.set("code", e.code(source.upstream_code()));
+        ext.set("httpStatus", e.status().as_u16());
+        ext.set("message", e.message());
+    })
+}
+
+/// Exposes how the LLM answer was obtained in the response `extensions.llm`.
+fn report_llm_stats(ctx: &Context<'_>, stats: LlmCallStats) {
+    if let Some(extensions) = ctx.data_opt::<ResponseExtensions>() {
+        extensions.set(
+            "llm",
+            async_graphql::value!({
+                "cached": stats.cached,
+                "attempts": stats.attempts,
+                "repaired": stats.repaired,
+            }),
+        );
+    }
+}
+
+/// The query, trimmed and in NFC, see [`validate_query`].
 fn validate_params(
     query: &str,
     lang_from_iso3: &str,
     lang_to_iso3: &str,
-) -> async_graphql::Result<()> {
-    let query = query.trim();
+) -> async_graphql::Result<String> {
+    let query = validate_query(query)?;
+    if lang_from_iso3.len() != 3 || lang_to_iso3.len() != 3 {
+        return Err(Error::new("languages must be ISO-3 (3 letters)")
+            .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")));
+    }
+    Ok(query)
+}
+
+/// The query, trimmed and in Unicode NFC like the data of all sources, so
+/// that e.g. a decomposed "ü" is found.
+fn validate_query(query: &str) -> async_graphql::Result<String> {
+    let query = normalize::nfc(query);
     if query.is_empty() {
         return Err(Error::new("query must not be empty")
             .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")));
@@ -77,11 +384,138 @@ fn validate_params(
                 .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")),
         );
     }
-    if lang_from_iso3.len() != 3 || lang_to_iso3.len() != 3 {
-        return Err(Error::new("languages must be ISO-3 (3 letters)")
-            .extend_with(|_, e| e.set("code", "BAD_USER_INPUT")));
-    }
-    Ok(())
+    Ok(query)
 }
 
 const MAX_QUERY_LEN: usize = 50;
+
+const MAX_AUTOCOMPLETE_LIMIT: u32 = 50;
+
+/// Each pivot language multiplies the work of a PanLex lookup.
+const MAX_PIVOT_LANGS: usize = 5;
+
+#[cfg(test)]
+mod tests {
+    use crate::app_state::{AppState, PanlexDb};
+    use crate::graphql::schema::build_schema;
+    use crate::llm::llm_provider::LlmConfig;
+    use crate::panlex::sqlite::Precomputed;
+    use serde_json::json;
+    use sqlx::SqlitePool;
+
+    fn offline_state() -> AppState {
+        // An empty DB: every PanLex query fails with "no such table".
+        let panlex = PanlexDb {
+            pool: SqlitePool::connect_lazy("sqlite::memory:").unwrap(),
+            precomputed: Precomputed::default(),
+        };
+        AppState::new(LlmConfig::Fake, None, None, Some(panlex), None, None, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn llm_works_offline() {
+        let schema = build_schema(offline_state());
+        let res = schema
+            .execute(
+                r#"{ llm(query: "Hund", langFromIso3: "deu", langToIso3: "eng") {
+                    ... on Forms { text source }
+                } }"#,
+            )
+            .await;
+
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        let data = res.data.into_json().unwrap();
+        assert_eq!(
+            data["llm"][0],
+            json!({ "text": "der Hund, -e", "source": "chatgpt" })
+        );
+        assert_eq!(
+            res.extensions
+                .get("llm")
+                .unwrap()
+                .clone()
+                .into_json()
+                .unwrap(),
+            json!({ "cached": false, "attempts": 1, "repaired": false })
+        );
+    }
+
+    #[tokio::test]
+    async fn kaikki_rejects_unsupported_language() {
+        let schema = build_schema(offline_state());
+        let res = schema
+            .execute(r#"{ kaikki(query: "koira", langIso3: "xxx") { __typename } }"#)
+            .await;
+
+        assert_eq!(res.errors.len(), 1);
+        assert_eq!(
+            res.errors[0].extensions.as_ref().unwrap().get("code"),
+            Some(&async_graphql::Value::from("BAD_USER_INPUT"))
+        );
+    }
+
+    #[tokio::test]
+    async fn examples_limit_is_validated() {
+        let schema = build_schema(offline_state());
+        let res = schema
+            .execute(
+                r#"{ examples(query: "Hund", langFromIso3: "deu", langToIso3: "eng", limit: 0) {
+                    source
+                } }"#,
+            )
+            .await;
+
+        assert_eq!(res.errors.len(), 1);
+        assert_eq!(
+            res.errors[0].extensions.as_ref().unwrap().get("code"),
+            Some(&async_graphql::Value::from("BAD_USER_INPUT"))
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_panlex_is_reported() {
+        let state = AppState::new(LlmConfig::Fake, None, None, None, None, None, None).unwrap();
+        let res = build_schema(state)
+            .execute(
+                r#"{ panlex(query: "Hund", langFromIso3: "deu", langToIso3: "eng") {
+                    __typename
+                } }"#,
+            )
+            .await;
+
+        assert_eq!(res.errors.len(), 1);
+        let extensions = res.errors[0].extensions.as_ref().unwrap();
+        assert_eq!(
+            extensions.get("code"),
+            Some(&async_graphql::Value::from("PANLEX_DISABLED"))
+        );
+        assert_eq!(
+            extensions.get("httpStatus"),
+            Some(&async_graphql::Value::from(503))
+        );
+    }
+
+    #[tokio::test]
+    async fn lookup_reports_failed_sources_next_to_data() {
+        let schema = build_schema(offline_state());
+        let res = schema
+            .execute(
+                r#"{ lookup(query: "Hund", langFromIso3: "deu", langToIso3: "eng",
+                            sources: [CHATGPT, PANLEX]) { __typename } }"#,
+            )
+            .await;
+
+        let data = res.data.into_json().unwrap();
+        assert!(!data["lookup"].as_array().unwrap().is_empty());
+        assert_eq!(res.errors.len(), 1);
+        let extensions = res.errors[0].extensions.as_ref().unwrap();
+        assert_eq!(
+            extensions.get("source"),
+            Some(&async_graphql::Value::from("panlex"))
+        );
+        assert_eq!(
+            extensions.get("code"),
+            Some(&async_graphql::Value::from("PANLEX_SQLITE"))
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest, NextRequest,
+};
+use async_graphql::{Request, Response, ServerResult, Value};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Per-request bag of values which end up in the `extensions` of the
+/// GraphQL response. Resolvers get it with `ctx.data::<ResponseExtensions>()`.
+#[derive(Clone, Default)]
+pub struct ResponseExtensions(Arc<Mutex<BTreeMap<String, Value>>>);
+
+impl ResponseExtensions {
+    pub fn set(&self, key: impl Into<String>, value: impl Into<Value>) {
+        self.0.lock().unwrap().insert(key.into(), value.into());
+    }
+
+    fn take(&self) -> BTreeMap<String, Value> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+/// Schema extension providing [`ResponseExtensions`] to every request.
+pub struct ResponseExtensionsFactory;
+
+impl ExtensionFactory for ResponseExtensionsFactory {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ResponseExtensionsExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct ResponseExtensionsExtension(ResponseExtensions);
+
+#[async_trait]
+impl Extension for ResponseExtensionsExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        next.run(ctx, request.data(self.0.clone())).await
+    }
+
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let mut response = next.run(ctx).await;
+        response.extensions.extend(self.0.take());
+        response
+    }
+}
@@ -1,14 +1,17 @@
+use super::mutation::Mutation;
 use super::query::Query;
+use super::response_extensions::ResponseExtensionsFactory;
 use crate::app_state::AppState;
 use async_graphql::extensions::{Logger, Tracing};
-use async_graphql::{EmptyMutation, EmptySubscription, Schema};
+use async_graphql::{EmptySubscription, Schema};
 
-pub type AppSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+pub type AppSchema = Schema<Query, Mutation, EmptySubscription>;
 
 pub fn build_schema(app_state: AppState) -> AppSchema {
-    Schema::build(Query, EmptyMutation, EmptySubscription)
+    Schema::build(Query, Mutation, EmptySubscription)
         .data(app_state)
         .extension(Logger)
         .extension(Tracing)
+        .extension(ResponseExtensionsFactory)
         .finish()
 }
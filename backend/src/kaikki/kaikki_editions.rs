@@ -0,0 +1,109 @@
+use crate::languages::{self, tsv_rows};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// The edition most languages are described in; it has a section for every
+/// language in `data/languages.tsv`.
+pub const ENGLISH_EDITION: &str = "eng";
+
+/// `(edition ISO 639-3, kaikki.org path)` from `data/kaikki_editions.tsv`.
+static EDITIONS: LazyLock<Vec<(&'static str, &'static str)>> = LazyLock::new(|| {
+    tsv_rows(include_str!("../../data/kaikki_editions.tsv"))
+        .map(|row| match row[..] {
+            [edition, path] => (edition, path),
+            _ => panic!("kaikki_editions.tsv: expected 2 columns, got {row:?}"),
+        })
+        .collect()
+});
+
+/// Section names by `(edition, language)` from `data/kaikki_sections.tsv`.
+static SECTIONS: LazyLock<HashMap<(&'static str, &'static str), &'static str>> =
+    LazyLock::new(|| {
+        tsv_rows(include_str!("../../data/kaikki_sections.tsv"))
+            .map(|row| match row[..] {
+                [edition, lang, section] => ((edition, lang), section),
+                _ => panic!("kaikki_sections.tsv: expected 3 columns, got {row:?}"),
+            })
+            .collect()
+    });
+
+fn edition_path(edition_iso3: &str) -> Option<&'static str> {
+    EDITIONS
+        .iter()
+        .find(|(edition, _)| *edition == edition_iso3)
+        .map(|(_, path)| *path)
+}
+
+/// Words are described in their own language's edition where kaikki.org
+/// publishes it, in the English edition otherwise.
+pub fn default_edition(lang_iso3: &str) -> &'static str {
+    EDITIONS
+        .iter()
+        .find(|(edition, _)| *edition == lang_iso3)
+        .map_or(ENGLISH_EDITION, |(edition, _)| *edition)
+}
+
+/// Name of the section of `lang_iso3` in the `edition_iso3` Wiktionary.
+fn section(edition_iso3: &str, lang_iso3: &str) -> Option<&'static str> {
+    if edition_iso3 == ENGLISH_EDITION {
+        return languages::by_iso3(lang_iso3).map(|l| l.name);
+    }
+    SECTIONS.get(&(edition_iso3, lang_iso3)).copied()
+}
+
+/// kaikki.org directory with the pages of the `lang_iso3` words described
+/// in the `edition_iso3` Wiktionary, e.g. "dictionary/German";
+/// `None` if kaikki.org does not publish it.
+pub fn section_path(lang_iso3: &str, edition_iso3: &str) -> Option<String> {
+    Some(format!(
+        "{}/{}",
+        edition_path(edition_iso3)?,
+        section(edition_iso3, lang_iso3)?
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn own_editions_are_preferred() {
+        assert_eq!(default_edition("deu"), "deu");
+        assert_eq!(default_edition("fin"), "eng");
+        assert_eq!(section_path("rus", "rus").unwrap(), "ruwiktionary/Русский");
+        assert_eq!(section_path("fin", "eng").unwrap(), "dictionary/Finnish");
+    }
+
+    #[test]
+    fn words_can_be_described_in_other_editions() {
+        assert_eq!(section_path("deu", "eng").unwrap(), "dictionary/German");
+        assert_eq!(section_path("eng", "deu").unwrap(), "dewiktionary/Englisch");
+        assert_eq!(section_path("fin", "deu").unwrap(), "dewiktionary/Finnisch");
+        // Languages the edition has no name for in the ISO 639-3 translations
+        assert_eq!(section_path("aaa", "deu"), None);
+        assert_eq!(section_path("deu", "xxx"), None);
+    }
+
+    #[test]
+    fn data_files_refer_to_known_languages() {
+        for (edition, _) in EDITIONS.iter() {
+            assert!(languages::by_iso3(edition).is_some(), "{edition}");
+            assert!(section_path(edition, edition).is_some(), "{edition}");
+        }
+        for (edition, lang) in SECTIONS.keys() {
+            assert!(edition_path(edition).is_some(), "{edition}");
+            assert!(languages::by_iso3(lang).is_some(), "{lang}");
+        }
+    }
+
+    #[test]
+    fn less_common_languages_have_sections() {
+        assert_eq!(section_path("kab", "eng").unwrap(), "dictionary/Kabyle");
+        assert_eq!(section_path("kab", "fra").unwrap(), "frwiktionary/Kabyle");
+        assert_eq!(
+            section_path("kab", "rus").unwrap(),
+            "ruwiktionary/Кабильский"
+        );
+        assert_eq!(section_path("tok", "eng").unwrap(), "dictionary/Toki Pona");
+    }
+}
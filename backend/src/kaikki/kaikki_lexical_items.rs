@@ -0,0 +1,695 @@
+use super::kaikki_editions::default_edition;
+use super::kaikki_proxy::kaikki_url;
+use super::kaikki_store::KaikkiStore;
+use crate::app_state::LocalDb;
+use crate::model::{
+    LexicalItemDetail, Sentence, TranslationsSet,
+    lexical_item_detail::{
+        Etymology, Example, Explanation, Forms, GrammaticalGender, PartOfSpeech, Pronunciation,
+    },
+};
+use crate::upstream::source_error::SourceError;
+use crate::upstream::upstream_proxy::Upstream;
+use crate::util::truncate;
+use axum::http::StatusCode;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use tracing::{error, warn};
+
+/// Parses the Wiktextract entries of `query` into forms, pronunciations,
+/// etymology, and per sense an explanation (its glosses) followed by its
+/// examples.
+///
+/// Entries come from the `local` DB when there is one. The kaikki.org page
+/// of `query` is fetched through `upstream` when there is no local DB, or when
+/// it has no entries and falls back to kaikki.org.
+///
+/// `edition_iso3` selects the Wiktionary edition describing the word; by
+/// default all stored editions are used, or the `default_edition` live.
+pub async fn get(
+    http_client: &Client,
+    upstream: &Upstream,
+    local: Option<&LocalDb<KaikkiStore>>,
+    query: &str,
+    lang_iso3: &str,
+    edition_iso3: Option<&str>,
+    url: Option<&str>,
+) -> Result<Vec<LexicalItemDetail>, SourceError> {
+    let query = query.trim();
+    if let Some(LocalDb {
+        store,
+        live_fallback,
+    }) = local
+    {
+        let rows = store
+            .lookup(query, lang_iso3, edition_iso3)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "failed to read local Kaikki DB");
+                SourceError::Internal(e.to_string())
+            })?;
+        if !rows.is_empty() || !live_fallback {
+            return Ok(parse_lines(
+                rows.iter()
+                    .map(|r| (r.line.as_str(), r.edition_iso3.as_str())),
+                lang_iso3,
+            ));
+        }
+    }
+    let edition_iso3 = edition_iso3.unwrap_or_else(|| default_edition(lang_iso3));
+    get_live(http_client, upstream, query, lang_iso3, edition_iso3, url).await
+}
+
+/// Fetches the Wiktextract JSONL page of `query` from kaikki.org.
+async fn get_live(
+    http_client: &Client,
+    upstream: &Upstream,
+    query: &str,
+    lang_iso3: &str,
+    edition_iso3: &str,
+    url: Option<&str>,
+) -> Result<Vec<LexicalItemDetail>, SourceError> {
+    let url = match url {
+        Some(url) => url.to_string(),
+        None => kaikki_url(query, lang_iso3, edition_iso3)
+            .ok_or_else(|| SourceError::BadInput("unsupported lang".to_string()))?,
+    };
+    let url = Url::parse(&url).map_err(|e| SourceError::Internal(e.to_string()))?;
+
+    let res = upstream.get(http_client, url).await?;
+    if res.status == StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    let body = String::from_utf8_lossy(&res.body);
+    if !res.status.is_success() {
+        error!(status = %res.status, body = %truncate(&body), "Kaikki non-success");
+        return Err(SourceError::Upstream(res.status, body.into_owned()));
+    }
+
+    Ok(parse_lines(
+        body.lines().map(|l| (l, edition_iso3)),
+        lang_iso3,
+    ))
+}
+
+#[derive(Deserialize)]
+struct KaikkiEntry {
+    #[serde(default)]
+    word: String,
+    pos: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    forms: Vec<KaikkiForm>,
+    #[serde(default)]
+    head_templates: Vec<KaikkiHeadTemplate>,
+    #[serde(default)]
+    sounds: Vec<KaikkiSound>,
+    etymology_text: Option<String>,
+    #[serde(default)]
+    senses: Vec<KaikkiSense>,
+}
+
+#[derive(Deserialize)]
+struct KaikkiForm {
+    form: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl KaikkiForm {
+    fn has(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Rows of inflection tables which describe the table, not a word form.
+    fn is_meta(&self) -> bool {
+        self.has("table-tags") || self.has("inflection-template") || self.has("class")
+    }
+}
+
+#[derive(Deserialize)]
+struct KaikkiHeadTemplate {
+    #[serde(default)]
+    expansion: String,
+}
+
+#[derive(Deserialize)]
+struct KaikkiSound {
+    ipa: Option<String>,
+    mp3_url: Option<String>,
+    ogg_url: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct KaikkiSense {
+    #[serde(default)]
+    glosses: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    examples: Vec<KaikkiExample>,
+}
+
+#[derive(Deserialize)]
+struct KaikkiExample {
+    #[serde(default)]
+    text: String,
+    /// Newer Wiktextract versions use `translation`, older ones `english`
+    #[serde(alias = "english")]
+    translation: Option<String>,
+}
+
+/// Builds structured forms from the head of an entry; `None` for entries
+/// without a part of speech (e.g. redirects).
+fn entry_forms(entry: &KaikkiEntry, lang_iso3: &str, source: &str) -> Option<Forms> {
+    let pos = PartOfSpeech::parse(entry.pos.as_deref()?);
+    let text = entry
+        .head_templates
+        .iter()
+        .map(|h| h.expansion.trim())
+        .find(|e| !e.is_empty())
+        .unwrap_or(&entry.word)
+        .to_string();
+
+    let mut forms = Forms::new(text, source);
+    forms.part_of_speech = Some(pos);
+    let forms_of = |pred: &dyn Fn(&KaikkiForm) -> bool| {
+        entry
+            .forms
+            .iter()
+            .filter(|f| !f.is_meta() && !f.form.trim().is_empty())
+            .find(|f| pred(f))
+            .map(|f| f.form.trim().to_string())
+    };
+
+    match pos {
+        PartOfSpeech::Noun | PartOfSpeech::ProperNoun => {
+            forms.gender = entry
+                .tags
+                .iter()
+                .chain(entry.senses.iter().flat_map(|s| s.tags.iter()))
+                .find_map(|t| GrammaticalGender::parse(t));
+            forms.plural = forms_of(&|f| {
+                f.has("plural")
+                    && !f.has("diminutive")
+                    && !f.has("definite")
+                    && [
+                        "genitive",
+                        "dative",
+                        "accusative",
+                        "instrumental",
+                        "prepositional",
+                    ]
+                    .iter()
+                    .all(|case| !f.has(case))
+            });
+            forms.article = forms
+                .gender
+                .and_then(|g| definite_article(lang_iso3, g, &entry.word));
+        }
+        PartOfSpeech::Verb => {
+            let present = forms_of(&|f| {
+                f.has("present")
+                    && f.has("third-person")
+                    && f.has("singular")
+                    && !f.has("subjunctive")
+            });
+            let past = forms_of(&|f| {
+                (f.has("past") || f.has("preterite"))
+                    && !f.has("participle")
+                    && !f.has("plural")
+                    && !f.has("second-person")
+                    && !f.has("subjunctive")
+            });
+            let participle = forms_of(&|f| f.has("participle") && f.has("past"));
+            if present.is_some() || past.is_some() || participle.is_some() {
+                forms.verb_principal_parts = std::iter::once(entry.word.clone())
+                    .chain(present)
+                    .chain(past)
+                    .chain(participle)
+                    .collect();
+            }
+        }
+        _ => {}
+    }
+    Some(forms)
+}
+
+/// Singular definite article for the languages where it is part of
+/// learning a noun.
+fn definite_article(lang_iso3: &str, gender: GrammaticalGender, word: &str) -> Option<String> {
+    use GrammaticalGender::*;
+    let article = match (lang_iso3, gender) {
+        ("deu", Masculine) => "der",
+        ("deu", Feminine) => "die",
+        ("deu", Neuter) => "das",
+        ("fra", Masculine | Feminine)
+            if word.starts_with(['a', 'e', 'i', 'o', 'u', 'é', 'è', 'â', 'î', 'ô']) =>
+        {
+            "l'"
+        }
+        ("fra", Masculine) => "le",
+        ("fra", Feminine) => "la",
+        ("spa", Masculine) => "el",
+        ("spa", Feminine) => "la",
+        _ => return None,
+    };
+    Some(article.to_string())
+}
+
+fn pronunciation(sound: KaikkiSound, source: &str) -> Option<Pronunciation> {
+    let ipa = sound.ipa.filter(|i| !i.trim().is_empty());
+    let audio_url = sound.mp3_url.or(sound.ogg_url);
+    // Rhymes, homophones etc. carry neither
+    if ipa.is_none() && audio_url.is_none() {
+        return None;
+    }
+    Some(Pronunciation {
+        ipa,
+        audio_url,
+        tags: sound.tags,
+        source: source.to_string(),
+    })
+}
+
+/// Example translations are in the language of the Wiktionary edition.
+fn example(
+    example: KaikkiExample,
+    lang_iso3: &str,
+    edition_iso3: &str,
+    source: &str,
+) -> Option<Example> {
+    if example.text.trim().is_empty() {
+        return None;
+    }
+    let translations = match example.translation {
+        Some(t) if !t.trim().is_empty() && edition_iso3 != lang_iso3 => {
+            vec![Sentence::new(t.trim(), edition_iso3, source)]
+        }
+        _ => Vec::new(),
+    };
+    Some(Example {
+        translations_set: TranslationsSet {
+            original: Sentence::new(example.text.trim(), lang_iso3, source),
+            translations,
+            translations_qualities: None,
+        },
+        source: source.to_string(),
+    })
+}
+
+/// Parses JSONL `lines`, each paired with the language of the Wiktionary
+/// edition it was extracted from.
+fn parse_lines<'a>(
+    lines: impl IntoIterator<Item = (&'a str, &'a str)>,
+    lang_iso3: &str,
+) -> Vec<LexicalItemDetail> {
+    let source = "kaikki".to_string();
+    let mut out = Vec::<LexicalItemDetail>::new();
+    // Entries of one word often repeat its sounds and etymology
+    let mut push = |item: LexicalItemDetail| {
+        if !out.contains(&item) {
+            out.push(item);
+        }
+    };
+    for (line, edition_iso3) in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let entry: KaikkiEntry = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!(error = %e, sample = %truncate(line), "skipping invalid Kaikki line");
+                continue;
+            }
+        };
+        if let Some(forms) = entry_forms(&entry, lang_iso3, &source) {
+            push(LexicalItemDetail::Forms(forms));
+        }
+        for sound in entry.sounds {
+            if let Some(p) = pronunciation(sound, &source) {
+                push(LexicalItemDetail::Pronunciation(p));
+            }
+        }
+        if let Some(text) = entry.etymology_text.filter(|t| !t.trim().is_empty()) {
+            push(LexicalItemDetail::Etymology(Etymology {
+                text: text.trim().to_string(),
+                source: source.clone(),
+            }));
+        }
+        for sense in entry.senses {
+            if !sense.glosses.is_empty() {
+                push(LexicalItemDetail::Explanation(Explanation {
+                    text: sense.glosses.join("; "),
+                    source: source.clone(),
+                }));
+            }
+            for e in sense.examples {
+                if let Some(e) = example(e, lang_iso3, edition_iso3, &source) {
+                    push(LexicalItemDetail::Example(e));
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kaikki::kaikki_proxy::UPSTREAM;
+    use crate::upstream::circuit_breaker::UpstreamBreakers;
+    use mockito::Server;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    fn upstream() -> Upstream {
+        Upstream::new(UPSTREAM, UpstreamBreakers::default().kaikki)
+    }
+
+    const HUND_JSONL: &str = r#"{"word": "Hund", "lang": "Deutsch", "senses": [{"glosses": ["Haustier, das bellt"]}, {"glosses": ["Schimpfwort", "gemeiner Mensch"]}]}
+not json at all
+{"word": "Hund", "lang": "Deutsch", "senses": [{"tags": ["no-gloss"]}]}
+"#;
+
+    #[tokio::test]
+    async fn glosses_become_explanations() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/Hund.jsonl")
+            .with_status(200)
+            .with_body(HUND_JSONL)
+            .create();
+
+        let url = format!("{}/Hund.jsonl", server.url());
+        let items = get(
+            &Client::new(),
+            &upstream(),
+            None,
+            "Hund",
+            "deu",
+            None,
+            Some(&url),
+        )
+        .await
+        .expect("Ok");
+
+        let source = "kaikki".to_string();
+        assert_eq!(
+            items,
+            vec![
+                LexicalItemDetail::Explanation(Explanation {
+                    text: "Haustier, das bellt".into(),
+                    source: source.clone(),
+                }),
+                LexicalItemDetail::Explanation(Explanation {
+                    text: "Schimpfwort; gemeiner Mensch".into(),
+                    source,
+                }),
+            ]
+        );
+    }
+
+    const DEU_JSONL: &str = r#"{"word": "Hund", "pos": "noun", "head_templates": [{"expansion": "Hund m (strong, genitive Hundes, plural Hunde)"}], "forms": [{"form": "de-ndecl", "tags": ["table-tags"]}, {"form": "Hundes", "tags": ["genitive"]}, {"form": "Hunden", "tags": ["dative", "plural"]}, {"form": "Hunde", "tags": ["plural"]}], "senses": [{"glosses": ["dog"], "tags": ["masculine", "strong"]}]}
+{"word": "gehen", "pos": "verb", "head_templates": [{"expansion": "gehen (class 7 strong, third-person singular present geht, past tense ging, past participle gegangen, auxiliary sein)"}], "forms": [{"form": "gehe", "tags": ["first-person", "present", "singular"]}, {"form": "geht", "tags": ["present", "singular", "third-person"]}, {"form": "ging", "tags": ["past"]}, {"form": "gegangen", "tags": ["participle", "past"]}], "senses": [{"glosses": ["to walk"]}]}
+"#;
+
+    #[tokio::test]
+    async fn heads_become_structured_forms() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/Hund.jsonl")
+            .with_status(200)
+            .with_body(DEU_JSONL)
+            .create();
+
+        let url = format!("{}/Hund.jsonl", server.url());
+        let items = get(
+            &Client::new(),
+            &upstream(),
+            None,
+            "Hund",
+            "deu",
+            None,
+            Some(&url),
+        )
+        .await
+        .expect("Ok");
+
+        let LexicalItemDetail::Forms(noun) = &items[0] else {
+            panic!("expected forms, got {:?}", items[0]);
+        };
+        assert_eq!(noun.text, "Hund m (strong, genitive Hundes, plural Hunde)");
+        assert_eq!(noun.part_of_speech, Some(PartOfSpeech::Noun));
+        assert_eq!(noun.gender, Some(GrammaticalGender::Masculine));
+        assert_eq!(noun.article.as_deref(), Some("der"));
+        assert_eq!(noun.plural.as_deref(), Some("Hunde"));
+        assert!(noun.verb_principal_parts.is_empty());
+
+        let LexicalItemDetail::Forms(verb) = &items[2] else {
+            panic!("expected forms, got {:?}", items[2]);
+        };
+        assert_eq!(verb.part_of_speech, Some(PartOfSpeech::Verb));
+        assert_eq!(verb.gender, None);
+        assert_eq!(
+            verb.verb_principal_parts,
+            vec!["gehen", "geht", "ging", "gegangen"]
+        );
+    }
+
+    const ENG_JSONL: &str = r#"{"word": "dog", "pos": "noun", "etymology_text": "From Middle English dogge.", "sounds": [{"ipa": "/dɒɡ/", "tags": ["Received-Pronunciation"]}, {"audio": "en-us-dog.ogg", "ogg_url": "https://example.org/en-us-dog.ogg", "mp3_url": "https://example.org/en-us-dog.mp3"}, {"rhymes": "-ɒɡ"}], "senses": [{"glosses": ["A mammal."], "examples": [{"text": "The dog barked.", "type": "example"}, {"text": ""}]}]}
+{"word": "dog", "pos": "verb", "etymology_text": "From Middle English dogge.", "sounds": [{"ipa": "/dɒɡ/", "tags": ["Received-Pronunciation"]}], "senses": [{"glosses": ["To pursue."]}]}
+"#;
+
+    #[tokio::test]
+    async fn sounds_etymology_and_examples_are_parsed() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/dog.jsonl")
+            .with_status(200)
+            .with_body(ENG_JSONL)
+            .create();
+
+        let url = format!("{}/dog.jsonl", server.url());
+        let items = get(
+            &Client::new(),
+            &upstream(),
+            None,
+            "dog",
+            "eng",
+            None,
+            Some(&url),
+        )
+        .await
+        .expect("Ok");
+        let source = "kaikki".to_string();
+
+        // The verb repeats the sound and etymology of the noun
+        assert!(matches!(items[6], LexicalItemDetail::Forms(_)));
+        assert_eq!(
+            items[1..6]
+                .iter()
+                .chain(&items[7..])
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec![
+                LexicalItemDetail::Pronunciation(Pronunciation {
+                    ipa: Some("/dɒɡ/".into()),
+                    audio_url: None,
+                    tags: vec!["Received-Pronunciation".into()],
+                    source: source.clone(),
+                }),
+                LexicalItemDetail::Pronunciation(Pronunciation {
+                    ipa: None,
+                    audio_url: Some("https://example.org/en-us-dog.mp3".into()),
+                    tags: vec![],
+                    source: source.clone(),
+                }),
+                LexicalItemDetail::Etymology(Etymology {
+                    text: "From Middle English dogge.".into(),
+                    source: source.clone(),
+                }),
+                LexicalItemDetail::Explanation(Explanation {
+                    text: "A mammal.".into(),
+                    source: source.clone(),
+                }),
+                LexicalItemDetail::Example(Example {
+                    translations_set: TranslationsSet {
+                        original: Sentence::new("The dog barked.", "eng", &source),
+                        translations: vec![],
+                        translations_qualities: None,
+                    },
+                    source: source.clone(),
+                }),
+                LexicalItemDetail::Explanation(Explanation {
+                    text: "To pursue.".into(),
+                    source: source.clone(),
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn local_store_is_preferred_and_falls_back_only_when_enabled() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("connect :memory:");
+        let store = KaikkiStore::new(pool).await.expect("store");
+        let dump = r#"{"word": "Hund", "lang_code": "de", "senses": [{"glosses": ["dog"], "examples": [{"text": "Der Hund bellt.", "english": "The dog barks."}]}]}"#;
+        store.ingest(dump.as_bytes(), None, "eng").await.unwrap();
+
+        let mut server = Server::new_async().await;
+        let live = server
+            .mock("GET", "/Katze.jsonl")
+            .with_status(200)
+            .with_body(r#"{"word": "Katze", "senses": [{"glosses": ["Haustier"]}]}"#)
+            .expect(1)
+            .create();
+        let url = format!("{}/Katze.jsonl", server.url());
+        let client = Client::new();
+        let upstream = upstream();
+        let local = |live_fallback| LocalDb {
+            store: store.clone(),
+            live_fallback,
+        };
+
+        let items = get(
+            &client,
+            &upstream,
+            Some(&local(true)),
+            "Hund",
+            "deu",
+            None,
+            Some(&url),
+        )
+        .await
+        .expect("Ok");
+        let source = "kaikki".to_string();
+        assert_eq!(
+            items,
+            vec![
+                LexicalItemDetail::Explanation(Explanation {
+                    text: "dog".into(),
+                    source: source.clone(),
+                }),
+                LexicalItemDetail::Example(Example {
+                    translations_set: TranslationsSet {
+                        original: Sentence::new("Der Hund bellt.", "deu", &source),
+                        translations: vec![Sentence::new("The dog barks.", "eng", &source)],
+                        translations_qualities: None,
+                    },
+                    source: source.clone(),
+                }),
+            ]
+        );
+
+        let items = get(
+            &client,
+            &upstream,
+            Some(&local(false)),
+            "Katze",
+            "deu",
+            None,
+            Some(&url),
+        )
+        .await
+        .expect("Ok");
+        assert!(items.is_empty());
+
+        let items = get(
+            &client,
+            &upstream,
+            Some(&local(true)),
+            "Katze",
+            "deu",
+            None,
+            Some(&url),
+        )
+        .await
+        .expect("Ok");
+        assert_eq!(items.len(), 1);
+        live.assert();
+    }
+
+    #[tokio::test]
+    async fn live_pages_are_cached() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/Hund.jsonl")
+            .with_body(HUND_JSONL)
+            .expect(1)
+            .create();
+
+        let url = format!("{}/Hund.jsonl", server.url());
+        let client = Client::new();
+        let upstream = upstream();
+        for _ in 0..2 {
+            let items = get(&client, &upstream, None, "Hund", "deu", None, Some(&url))
+                .await
+                .expect("Ok");
+            assert_eq!(items.len(), 2);
+        }
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn not_found_is_empty() {
+        let mut server = Server::new_async().await;
+        let _m = server.mock("GET", "/Nope.jsonl").with_status(404).create();
+
+        let url = format!("{}/Nope.jsonl", server.url());
+        let items = get(
+            &Client::new(),
+            &upstream(),
+            None,
+            "Nope",
+            "deu",
+            None,
+            Some(&url),
+        )
+        .await
+        .expect("Ok");
+        assert!(items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unsupported_lang_is_bad_request() {
+        let err = get(&Client::new(), &upstream(), None, "Hund", "xxx", None, None)
+            .await
+            .expect_err("Err");
+        assert_eq!(err, SourceError::BadInput("unsupported lang".into()));
+    }
+
+    #[tokio::test]
+    async fn upstream_bad_requests_are_not_ours() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/Hund.jsonl")
+            .with_status(400)
+            .with_body("bad")
+            .create();
+
+        let url = format!("{}/Hund.jsonl", server.url());
+        let err = get(
+            &Client::new(),
+            &upstream(),
+            None,
+            "Hund",
+            "deu",
+            None,
+            Some(&url),
+        )
+        .await
+        .expect_err("Err");
+        assert_eq!(
+            err,
+            SourceError::Upstream(StatusCode::BAD_REQUEST, "bad".into())
+        );
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+    }
+}
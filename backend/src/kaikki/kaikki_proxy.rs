@@ -1,86 +1,98 @@
 use axum::{
     body::Body,
     extract::{Query, State},
-    http::{header, Response, StatusCode},
+    http::{Response, StatusCode, header},
+    response::IntoResponse,
 };
+use reqwest::Url;
 use serde::Deserialize;
+use std::time::Duration;
 use tracing::error;
 
+use super::kaikki_editions::{default_edition, section_path};
 use crate::app_state::AppState;
+use crate::upstream::{proxy_error::ProxyError, upstream_proxy::UpstreamConfig};
+
+/// kaikki.org serves static files which change with each weekly dump.
+pub const UPSTREAM: UpstreamConfig = UpstreamConfig {
+    name: "Kaikki",
+    error_code: "UPSTREAM_KAIKKI",
+    timeout: Duration::from_secs(10),
+    retries: 2,
+    backoff: Duration::from_millis(200),
+    default_max_age: Duration::from_secs(24 * 60 * 60),
+    cache_bytes: 64 * 1024 * 1024,
+};
 
 #[derive(Deserialize)]
 pub struct KaikkiQuery {
     pub query: String,
     pub lang_iso3: String,
+    /// Wiktionary edition to take the entries from; see `default_edition`.
+    pub edition_iso3: Option<String>,
 }
 
 pub async fn kaikki_proxy(
     State(state): State<AppState>,
     Query(params): Query<KaikkiQuery>,
-) -> Result<Response<Body>, (StatusCode, String)> {
+) -> Result<Response<Body>, ProxyError> {
     let query = params.query.trim();
     if query.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "query must not be empty".into()));
+        return Err(ProxyError::bad_request("query must not be empty"));
     }
 
-    let sub = match subwiktionary_of(params.lang_iso3.trim()) {
-        Some(s) => s,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "unsupported lang".into(),
-            ));
-        }
-    };
-
-    let url = format!(
-        "https://kaikki.org/{}/meaning/{}",
-        sub,
-        query_page_postfix(query),
-    );
-
-    let upstream = match state.http_client().get(&url).send().await {
-        Ok(r) => r,
-        Err(e) => {
-            error!(error = %e, %url, "network error talking to Kaikki");
-            return Err((StatusCode::BAD_GATEWAY, "Kaikki upstream error".into()));
+    let lang_iso3 = params.lang_iso3.trim();
+    let edition_iso3 = params.edition_iso3.as_deref().map(str::trim);
+    if let Some(store) = state.kaikki_store() {
+        let rows = store
+            .lookup(query, lang_iso3, edition_iso3)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "failed to read local Kaikki DB");
+                ProxyError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "KAIKKI_SQLITE",
+                    "Kaikki DB error",
+                )
+            })?;
+        if !rows.is_empty() {
+            let body: String = rows.into_iter().map(|r| r.line + "\n").collect();
+            return Ok((
+                [(header::CONTENT_TYPE, "application/jsonl; charset=utf-8")],
+                body,
+            )
+                .into_response());
         }
-    };
-
-    let status = upstream.status();
-    let headers = upstream.headers().clone();
-
-    let body_bytes = match upstream.bytes().await {
-        Ok(b) => b,
-        Err(e) => {
-            error!(error = %e, "failed to read Kaikki response body");
-            return Err((StatusCode::BAD_GATEWAY, "Kaikki upstream error".into()));
+        if !state.kaikki_live_fallback() {
+            return Err(ProxyError::new(
+                StatusCode::NOT_FOUND,
+                "NOT_FOUND",
+                "not found",
+            ));
         }
-    };
-
-    // Build a response that mirrors Kaikki as closely as is reasonable:
-    let mut builder = Response::builder().status(status);
-
-    // Preserve content-type if present
-    if let Some(ct) = headers.get(header::CONTENT_TYPE) {
-        builder = builder.header(header::CONTENT_TYPE, ct);
     }
 
-    let resp = builder
-        .body(Body::from(body_bytes))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    Ok(resp)
+    let edition_iso3 = edition_iso3.unwrap_or_else(|| default_edition(lang_iso3));
+    let url = kaikki_url(query, lang_iso3, edition_iso3)
+        .ok_or_else(|| ProxyError::bad_request("unsupported lang"))?;
+    let url = Url::parse(&url).map_err(|e| ProxyError::bad_request(e.to_string()))?;
+
+    let upstream = state
+        .kaikki_upstream()
+        .get(state.http_client(), url)
+        .await
+        .map_err(|e| ProxyError::from_source(UPSTREAM.error_code, e))?;
+    Ok(upstream.into_response())
 }
 
-fn subwiktionary_of(lang_iso3: &str) -> Option<&'static str> {
-    match lang_iso3 {
-        "rus" => Some("ruwiktionary/Русский"),
-        "eng" => Some("dictionary/English"),
-        "deu" => Some("dewiktionary/Deutsch"),
-        "fra" => Some("frwiktionary/Français"),
-        _ => None,
-    }
+/// URL of the kaikki.org page of `query` in `lang_iso3`, as described in
+/// the `edition_iso3` Wiktionary (see `kaikki_editions::default_edition`).
+pub(crate) fn kaikki_url(query: &str, lang_iso3: &str, edition_iso3: &str) -> Option<String> {
+    Some(format!(
+        "https://kaikki.org/{}/meaning/{}",
+        section_path(lang_iso3, edition_iso3)?,
+        query_page_postfix(query),
+    ))
 }
 
 fn query_page_postfix(query: &str) -> String {
@@ -0,0 +1,284 @@
+use crate::languages;
+use crate::util::truncate;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tracing::{info, warn};
+
+/// Rows are inserted in transactions of this size while ingesting.
+const INGEST_BATCH: usize = 10_000;
+
+/// Local copy of a Wiktextract JSONL dump, indexed by word and language.
+///
+/// Lines are stored verbatim and parsed on lookup, exactly as if they were
+/// fetched from kaikki.org.
+#[derive(Clone)]
+pub struct KaikkiStore {
+    pool: SqlitePool,
+}
+
+/// One stored dump line.
+pub struct KaikkiRow {
+    pub line: String,
+    /// Language of the Wiktionary edition the line was extracted from.
+    pub edition_iso3: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct IngestStats {
+    pub inserted: u64,
+    /// Lines of other languages than the requested one
+    pub filtered: u64,
+    /// Invalid lines and lines of languages without a known ISO 639-3 code
+    pub skipped: u64,
+}
+
+#[derive(Deserialize)]
+struct DumpLine {
+    word: Option<String>,
+    lang_code: Option<String>,
+}
+
+impl KaikkiStore {
+    pub async fn new(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS kaikki_entries (
+                id        INTEGER PRIMARY KEY,
+                word      TEXT NOT NULL,
+                lang_iso3 TEXT NOT NULL,
+                edition   TEXT NOT NULL,
+                line      TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS kaikki_entries_word ON kaikki_entries (word, lang_iso3)",
+        )
+        .execute(&pool)
+        .await?;
+        Ok(Self { pool })
+    }
+
+    /// Stores every line of the dump `reader` whose language is
+    /// `lang_iso3` (all languages if `None`). `edition_iso3` is the language
+    /// of the Wiktionary edition the dump was extracted from.
+    ///
+    /// Entries previously ingested from the same edition for the same
+    /// languages are replaced. The dump is loaded into a staging table
+    /// first, so that a failed ingest leaves the previous entries in place.
+    pub async fn ingest(
+        &self,
+        mut reader: impl AsyncBufRead + Unpin,
+        lang_iso3: Option<&str>,
+        edition_iso3: &str,
+    ) -> Result<IngestStats, sqlx::Error> {
+        let mut stats = IngestStats::default();
+        for ddl in [
+            "DROP TABLE IF EXISTS kaikki_entries_staging",
+            r#"
+            CREATE TABLE kaikki_entries_staging (
+                id        INTEGER PRIMARY KEY,
+                word      TEXT NOT NULL,
+                lang_iso3 TEXT NOT NULL,
+                line      TEXT NOT NULL
+            )
+            "#,
+        ] {
+            sqlx::query(ddl).execute(&self.pool).await?;
+        }
+        let mut tx = self.pool.begin().await?;
+
+        let mut line = String::new();
+        let mut in_batch = 0;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                break;
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let (word, line_lang) = match serde_json::from_str::<DumpLine>(trimmed) {
+                Ok(DumpLine {
+                    word: Some(word),
+                    lang_code: Some(code),
+                }) => match languages::by_wiktionary_code(&code) {
+                    Some(lang) => (word, lang.iso3),
+                    None => {
+                        stats.skipped += 1;
+                        continue;
+                    }
+                },
+                Ok(_) => {
+                    stats.skipped += 1;
+                    continue;
+                }
+                Err(e) => {
+                    warn!(error = %e, sample = %truncate(trimmed), "skipping invalid dump line");
+                    stats.skipped += 1;
+                    continue;
+                }
+            };
+            if lang_iso3.is_some_and(|l| l != line_lang) {
+                stats.filtered += 1;
+                continue;
+            }
+
+            sqlx::query(
+                "INSERT INTO kaikki_entries_staging (word, lang_iso3, line) VALUES (?1, ?2, ?3)",
+            )
+            .bind(word)
+            .bind(line_lang)
+            .bind(trimmed)
+            .execute(&mut *tx)
+            .await?;
+            stats.inserted += 1;
+
+            in_batch += 1;
+            if in_batch == INGEST_BATCH {
+                tx.commit().await?;
+                tx = self.pool.begin().await?;
+                in_batch = 0;
+                info!(inserted = stats.inserted, "ingesting Kaikki dump");
+            }
+        }
+        tx.commit().await?;
+
+        info!("replacing Kaikki entries");
+        let mut tx = self.pool.begin().await?;
+        match lang_iso3 {
+            Some(lang) => {
+                sqlx::query("DELETE FROM kaikki_entries WHERE edition = ?1 AND lang_iso3 = ?2")
+                    .bind(edition_iso3)
+                    .bind(lang)
+            }
+            None => sqlx::query("DELETE FROM kaikki_entries WHERE edition = ?1").bind(edition_iso3),
+        }
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO kaikki_entries (word, lang_iso3, edition, line)
+            SELECT word, lang_iso3, ?1, line FROM kaikki_entries_staging ORDER BY id
+            "#,
+        )
+        .bind(edition_iso3)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DROP TABLE kaikki_entries_staging")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(stats)
+    }
+
+    /// All stored entries of `word` in `lang_iso3`, in dump order; only
+    /// those of the `edition_iso3` Wiktionary if given.
+    pub async fn lookup(
+        &self,
+        word: &str,
+        lang_iso3: &str,
+        edition_iso3: Option<&str>,
+    ) -> Result<Vec<KaikkiRow>, sqlx::Error> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT line, edition FROM kaikki_entries
+            WHERE word = ?1 AND lang_iso3 = ?2 AND (?3 IS NULL OR edition = ?3)
+            ORDER BY id
+            "#,
+        )
+        .bind(word)
+        .bind(lang_iso3)
+        .bind(edition_iso3)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(line, edition_iso3)| KaikkiRow { line, edition_iso3 })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const DUMP: &str = r#"{"word": "Hund", "lang_code": "de", "pos": "noun"}
+{"word": "dog", "lang_code": "en", "pos": "noun"}
+not json
+{"word": "Hund", "lang_code": "de", "pos": "name"}
+{"word": "koira", "lang_code": "fi", "pos": "noun"}
+{"word": "x", "lang_code": "qqq-unknown", "pos": "noun"}
+"#;
+
+    async fn new_store() -> KaikkiStore {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("connect :memory:");
+        KaikkiStore::new(pool).await.expect("store")
+    }
+
+    #[tokio::test]
+    async fn ingests_one_language_and_finds_its_entries() {
+        let store = new_store().await;
+        let stats = store
+            .ingest(DUMP.as_bytes(), Some("deu"), "eng")
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            IngestStats {
+                inserted: 2,
+                filtered: 2,
+                skipped: 2,
+            }
+        );
+
+        let rows = store.lookup("Hund", "deu", None).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[1].line.contains(r#""pos": "name""#));
+        assert_eq!(rows[0].edition_iso3, "eng");
+        assert!(store.lookup("dog", "eng", None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reingesting_replaces_entries() {
+        let store = new_store().await;
+        store.ingest(DUMP.as_bytes(), None, "eng").await.unwrap();
+        store.ingest(DUMP.as_bytes(), None, "eng").await.unwrap();
+
+        assert_eq!(store.lookup("Hund", "deu", None).await.unwrap().len(), 2);
+        assert_eq!(store.lookup("dog", "eng", None).await.unwrap().len(), 1);
+
+        store.ingest(DUMP.as_bytes(), None, "deu").await.unwrap();
+        assert_eq!(store.lookup("Hund", "deu", None).await.unwrap().len(), 4);
+        assert_eq!(
+            store
+                .lookup("Hund", "deu", Some("deu"))
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_ingest_keeps_previous_entries() {
+        let store = new_store().await;
+        store.ingest(DUMP.as_bytes(), None, "eng").await.unwrap();
+
+        // Invalid UTF-8 fails the read after the first line was staged.
+        let broken: &[u8] = b"{\"word\": \"Katze\", \"lang_code\": \"de\"}\n\xff\xfe\n";
+        assert!(store.ingest(broken, None, "eng").await.is_err());
+
+        assert_eq!(store.lookup("Hund", "deu", None).await.unwrap().len(), 2);
+        assert!(store.lookup("Katze", "deu", None).await.unwrap().is_empty());
+    }
+}
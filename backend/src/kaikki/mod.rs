@@ -1 +1,4 @@
+pub(crate) mod kaikki_editions;
+pub(crate) mod kaikki_lexical_items;
 pub mod kaikki_proxy;
+pub(crate) mod kaikki_store;
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// A language known to the backend, from `data/languages.tsv`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Language {
+    pub iso3: &'static str,
+    /// ISO 639-1 where one exists, otherwise an ISO 639-3-like code.
+    pub wiktionary_code: &'static str,
+    /// As used for section names of the English Wiktionary.
+    pub name: &'static str,
+}
+
+static LANGUAGES: LazyLock<Vec<Language>> = LazyLock::new(|| {
+    tsv_rows(include_str!("../data/languages.tsv"))
+        .map(|row| match row[..] {
+            [iso3, wiktionary_code, name] => Language {
+                iso3,
+                wiktionary_code,
+                name,
+            },
+            _ => panic!("languages.tsv: expected 3 columns, got {row:?}"),
+        })
+        .collect()
+});
+
+/// The languages by ISO 639-3 code; the table lists thousands, and ingests
+/// look up every line.
+static BY_ISO3: LazyLock<HashMap<&'static str, &'static Language>> =
+    LazyLock::new(|| LANGUAGES.iter().map(|l| (l.iso3, l)).collect());
+
+/// Several ISO 639-3 codes may share a Wiktionary code (e.g. Serbo-Croatian);
+/// the first one listed wins.
+static BY_WIKTIONARY_CODE: LazyLock<HashMap<&'static str, &'static Language>> =
+    LazyLock::new(|| {
+        let mut by_code = HashMap::new();
+        for l in LANGUAGES.iter() {
+            by_code.entry(l.wiktionary_code).or_insert(l);
+        }
+        by_code
+    });
+
+pub fn by_iso3(iso3: &str) -> Option<&'static Language> {
+    BY_ISO3.get(iso3).copied()
+}
+
+pub fn by_wiktionary_code(code: &str) -> Option<&'static Language> {
+    BY_WIKTIONARY_CODE.get(code).copied()
+}
+
+/// Non-empty, non-comment lines of a tab-separated data file.
+pub(crate) fn tsv_rows(data: &'static str) -> impl Iterator<Item = Vec<&'static str>> {
+    data.lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .map(|l| l.split('\t').map(str::trim).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique_and_well_formed() {
+        for (i, l) in LANGUAGES.iter().enumerate() {
+            assert_eq!(l.iso3.len(), 3, "{l:?}");
+            assert!(!l.name.is_empty(), "{l:?}");
+            assert!(
+                LANGUAGES[..i].iter().all(|other| other.iso3 != l.iso3),
+                "duplicated {l:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn lookups() {
+        assert_eq!(by_iso3("deu").unwrap().name, "German");
+        assert_eq!(by_wiktionary_code("de").unwrap().iso3, "deu");
+        assert_eq!(by_wiktionary_code("sh").unwrap().iso3, "hbs");
+        assert_eq!(by_iso3("srp").unwrap().name, "Serbo-Croatian");
+        assert!(by_iso3("xxx").is_none());
+    }
+
+    #[test]
+    fn all_iso_639_3_languages_are_listed() {
+        // ISO 639-3 has some 7,900 individual languages.
+        assert!(7_000 < LANGUAGES.len());
+        assert_eq!(by_iso3("kab").unwrap().name, "Kabyle");
+        assert_eq!(by_iso3("tok").unwrap().name, "Toki Pona");
+        assert_eq!(by_iso3("grc").unwrap().name, "Ancient Greek");
+        assert_eq!(by_iso3("msa").unwrap().name, "Malay");
+        assert_eq!(by_wiktionary_code("jbo").unwrap().name, "Lojban");
+        assert_eq!(by_wiktionary_code("ii").unwrap().iso3, "iii");
+    }
+}
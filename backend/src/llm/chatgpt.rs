@@ -1,23 +1,84 @@
-use super::chatgpt_structs::{ChatGPTRequest, ChatGPTResponse};
+use super::chatgpt_structs::{
+    ChatGPTRequest, ChatGPTResponse, ChatGPTTextConfig, ChatGPTTextFormat,
+};
+use super::llm_provider::{JsonSchemaFormat, LlmCompletion, LlmProvider, LlmUsage};
+use crate::upstream::source_error::SourceError;
 use crate::util::truncate;
+use async_trait::async_trait;
 use axum::http::StatusCode;
 use reqwest::Client;
 use tracing::error;
 
 const DEFAULT_MODEL: &str = "gpt-4.1";
 
+/// [`LlmProvider`] backed by the OpenAI Responses API.
+pub struct ChatGPTProvider {
+    http_client: Client,
+    chatgpt_key: String,
+    model: Option<String>,
+    url: Option<String>,
+}
+
+impl ChatGPTProvider {
+    pub fn new(
+        http_client: Client,
+        chatgpt_key: String,
+        model: Option<String>,
+        url: Option<String>,
+    ) -> Self {
+        Self {
+            http_client,
+            chatgpt_key,
+            model,
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ChatGPTProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        format: Option<&JsonSchemaFormat>,
+    ) -> Result<LlmCompletion, SourceError> {
+        request(
+            &self.http_client,
+            &self.chatgpt_key,
+            prompt,
+            format,
+            self.model.as_deref(),
+            self.url.as_deref(),
+        )
+        .await
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        true
+    }
+}
+
 pub async fn request(
     http_client: &Client,
     chatgpt_key: &str,
     query: &str,
+    format: Option<&JsonSchemaFormat>,
     model: Option<&str>,
     url: Option<&str>,
-) -> Result<String, (StatusCode, String)> {
+) -> Result<LlmCompletion, SourceError> {
     let model = model.unwrap_or(DEFAULT_MODEL);
     let query = query.replace('\n', " ").trim().to_string();
     let request_body = ChatGPTRequest {
         model,
         input: &query,
+        text: format.map(|f| ChatGPTTextConfig {
+            format: ChatGPTTextFormat {
+                r#type: "json_schema",
+                name: f.name,
+                schema: &f.schema,
+                strict: true,
+            },
+        }),
     };
 
     let url = url.unwrap_or("https://api.openai.com/v1/responses");
@@ -31,7 +92,10 @@ pub async fn request(
         Ok(r) => r,
         Err(e) => {
             error!(error = %e, "network error talking to upstream");
-            return Err((StatusCode::BAD_GATEWAY, e.to_string()));
+            return Err(SourceError::Upstream(
+                StatusCode::BAD_GATEWAY,
+                e.to_string(),
+            ));
         }
     };
 
@@ -39,14 +103,17 @@ pub async fn request(
     if !status.is_success() {
         let body = res.text().await.unwrap_or_default();
         error!(%status, body = %truncate(&body), "upstream non-success");
-        return Err((StatusCode::BAD_GATEWAY, body));
+        return Err(SourceError::Upstream(status, body));
     }
 
     let parsed: ChatGPTResponse = match res.json().await {
         Ok(p) => p,
         Err(e) => {
             error!(error = %e, "failed to deserialize upstream response");
-            return Err((StatusCode::BAD_GATEWAY, e.to_string()));
+            return Err(SourceError::Upstream(
+                StatusCode::BAD_GATEWAY,
+                e.to_string(),
+            ));
         }
     };
 
@@ -57,27 +124,36 @@ pub async fn request(
         .map(|c| c.text.clone())
         .ok_or_else(|| {
             error!("missing output[0].content[0].text in upstream response");
-            (
+            SourceError::Upstream(
                 StatusCode::BAD_GATEWAY,
                 "missing `output[0].content[0].text` in upstream response".into(),
             )
         })?;
 
-    Ok(answer)
+    Ok(LlmCompletion {
+        text: answer,
+        model: parsed.model,
+        usage: Some(LlmUsage {
+            input_tokens: parsed.usage.input_tokens.into(),
+            output_tokens: parsed.usage.output_tokens.into(),
+        }),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::upstream::circuit_breaker::CircuitBreaker;
     use axum::http::StatusCode;
     use mockito::{Matcher, Server};
     use serde_json::json;
+    use std::time::Duration;
 
     async fn call(
         response_status: usize,
         response_body: &str,
         input_sent: &str,
-    ) -> Result<String, (StatusCode, String)> {
+    ) -> Result<LlmCompletion, SourceError> {
         let mut server = Server::new_async().await;
 
         let _m = server
@@ -93,7 +169,7 @@ mod tests {
         let client = Client::new();
         let url = format!("{}/v1/responses", server.url());
 
-        request(&client, "test_key", input_sent, None, Some(&url)).await
+        request(&client, "test_key", input_sent, None, None, Some(&url)).await
     }
 
     const TEST_RESPONSE: &str = r#"
@@ -124,7 +200,55 @@ mod tests {
         let result = call(200, TEST_RESPONSE, "What is the answer to life?")
             .await
             .unwrap();
-        assert_eq!(result, "42 is the answer.");
+        assert_eq!(result.text, "42 is the answer.");
+        assert_eq!(result.model, "4o");
+        assert_eq!(
+            result.usage,
+            Some(LlmUsage {
+                input_tokens: 10,
+                output_tokens: 20,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn json_schema_is_sent_as_text_format() {
+        let mut server = Server::new_async().await;
+        let schema = json!({ "type": "object" });
+        let _m = server
+            .mock("POST", "/v1/responses")
+            .match_body(Matcher::Json(json!({
+                "model": DEFAULT_MODEL,
+                "input": "Answer",
+                "text": {
+                    "format": {
+                        "type": "json_schema",
+                        "name": "answer",
+                        "schema": schema,
+                        "strict": true
+                    }
+                }
+            })))
+            .with_status(200)
+            .with_body(TEST_RESPONSE)
+            .create();
+
+        let format = JsonSchemaFormat {
+            name: "answer",
+            schema: schema.clone(),
+        };
+        let url = format!("{}/v1/responses", server.url());
+        let result = request(
+            &Client::new(),
+            "key",
+            "Answer",
+            Some(&format),
+            None,
+            Some(&url),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.text, "42 is the answer.");
     }
 
     #[tokio::test]
@@ -133,11 +257,29 @@ mod tests {
 
         let result = call(500, err_body, "Will this fail?").await;
 
-        let Err((status, body)) = result else {
+        let Err(err) = result else {
             panic!("expected Err");
         };
-        assert_eq!(status, StatusCode::BAD_GATEWAY);
-        assert_eq!(body, err_body);
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(err.message(), err_body);
+    }
+
+    #[tokio::test]
+    async fn upstream_client_errors_are_bad_gateways_and_keep_the_circuit_closed() {
+        let err_body = r#"{"error":"invalid api key"}"#;
+        let breaker = CircuitBreaker::new("openai", 1, Duration::from_secs(30));
+        for _ in 0..2 {
+            let err = breaker
+                .call(call(401, err_body, "Who am I?"))
+                .await
+                .expect_err("Err");
+            assert_eq!(
+                err,
+                SourceError::Upstream(StatusCode::UNAUTHORIZED, err_body.to_string())
+            );
+            assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+        }
+        assert_eq!(breaker.status().state, "closed");
     }
 
     #[tokio::test]
@@ -148,10 +290,10 @@ mod tests {
         drop(server);
 
         let client = Client::new();
-        let err = request(&client, "test_key", "any", None, Some(&url))
+        let err = request(&client, "test_key", "any", None, None, Some(&url))
             .await
             .expect_err("should be Err");
 
-        assert_eq!(err.0, StatusCode::BAD_GATEWAY);
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
     }
 }
@@ -1,42 +1,201 @@
-use super::chatgpt;
+use super::forms_templates::{self, forms_template};
+use super::json_repair::{fill_missing_fields, repair_json};
+use super::lexical_cache::LexicalCache;
+use super::llm_provider::{JsonSchemaFormat, LlmProvider};
+use super::usage_ledger::UsageLedger;
 use crate::model::{
     LexicalItemDetail, Sentence, TranslationsSet,
-    lexical_item_detail::{Example, Explanation, Forms, Synonyms, WordTranslations},
+    lexical_item_detail::{
+        Example, Explanation, Forms, GrammaticalGender, PartOfSpeech, Synonyms, WordTranslations,
+    },
 };
+use crate::upstream::source_error::SourceError;
 use crate::util::truncate;
 use axum::http::StatusCode;
-use reqwest::Client;
-use serde::Deserialize;
-use tracing::error;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::{Span, debug, error, field, info, instrument, warn};
+
+/// Bump whenever `build_prompt` changes in a way that changes answers,
+/// so that cached answers to the old prompt are no longer served.
+pub const PROMPT_VERSION: i64 = 3;
+
+/// Version of cached answers: the prompt and the forms templates it embeds.
+pub const CACHE_VERSION: i64 = PROMPT_VERSION * 1000 + forms_templates::VERSION;
+
+/// How many times the model is asked before giving up on malformed JSON.
+const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Debug)]
+pub struct LlmLexicalItems {
+    pub items: Vec<LexicalItemDetail>,
+    pub stats: LlmCallStats,
+}
+
+/// How an answer was obtained.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LlmCallStats {
+    /// Served from the cache, without calling the model.
+    pub cached: bool,
+    /// Number of model calls made, 0 for cache hits.
+    pub attempts: u32,
+    /// Whether the accepted answer needed local JSON repair.
+    pub repaired: bool,
+}
 
 pub async fn request(
-    http_client: &Client,
-    chatgpt_key: &str,
+    llm: &dyn LlmProvider,
+    cache: Option<&LexicalCache>,
+    ledger: Option<&UsageLedger>,
+    query: &str,
+    lang_from_iso3: &str,
+    lang_to_iso3: &str,
+) -> Result<LlmLexicalItems, SourceError> {
+    let query = query.trim();
+    let cached = match cache {
+        Some(cache) => {
+            cache
+                .get::<ChatGPTLexicalResponse>(query, lang_from_iso3, lang_to_iso3, CACHE_VERSION)
+                .await
+        }
+        None => None,
+    };
+    let (resp, stats) = match cached {
+        Some(resp) => {
+            debug!(%query, lang_from_iso3, lang_to_iso3, "LLM cache hit");
+            let stats = LlmCallStats {
+                cached: true,
+                ..Default::default()
+            };
+            (resp, stats)
+        }
+        None => {
+            if let Some(ledger) = ledger
+                && ledger.budget_exceeded().await
+            {
+                warn!("daily LLM budget exceeded, refusing to call the model");
+                return Err(SourceError::BudgetExceeded(
+                    "daily LLM budget exceeded".to_string(),
+                ));
+            }
+            let (resp, stats) =
+                request_model(llm, ledger, query, lang_from_iso3, lang_to_iso3).await?;
+            if let Some(cache) = cache {
+                cache
+                    .put(query, lang_from_iso3, lang_to_iso3, CACHE_VERSION, &resp)
+                    .await;
+            }
+            (resp, stats)
+        }
+    };
+    Ok(LlmLexicalItems {
+        items: to_lexical_items(resp, query, lang_from_iso3, lang_to_iso3),
+        stats,
+    })
+}
+
+/// Asks the model until it replies with usable JSON, at most `MAX_ATTEMPTS` times.
+/// Replies are repaired locally first; only if that fails the model is asked
+/// again, with the parse error appended to the prompt.
+#[instrument(skip_all, fields(attempts = field::Empty, repaired = field::Empty))]
+async fn request_model(
+    llm: &dyn LlmProvider,
+    ledger: Option<&UsageLedger>,
     query: &str,
     lang_from_iso3: &str,
     lang_to_iso3: &str,
-    model: Option<&str>,
-    url: Option<&str>,
-) -> Result<Vec<LexicalItemDetail>, (StatusCode, String)> {
-    let prompt = build_prompt(query, lang_from_iso3, lang_to_iso3);
-    let raw = chatgpt::request(http_client, chatgpt_key, &prompt, model, url).await?;
-    let json = extract_json_object(&raw).unwrap_or_else(|| raw.trim().to_string());
-
-    let resp: ChatGPTLexicalResponse = serde_json::from_str(&json).map_err(|e| {
-        error!(error = %e, sample = %truncate(&json), "invalid JSON from model");
-        (
-            StatusCode::BAD_GATEWAY,
-            format!("invalid JSON from ChatGPT: {e}"),
-        )
-    })?;
+) -> Result<(ChatGPTLexicalResponse, LlmCallStats), SourceError> {
+    let structured = llm.supports_json_schema();
+    let base_prompt = build_prompt(query, lang_from_iso3, lang_to_iso3, !structured);
+    let format = structured.then(ChatGPTLexicalResponse::response_format);
+    let span = Span::current();
+
+    let mut prompt = base_prompt.clone();
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        span.record("attempts", attempt);
+        let completion = llm.complete(&prompt, format.as_ref()).await?;
+        if let (Some(ledger), Some(usage)) = (ledger, &completion.usage) {
+            ledger
+                .record(&completion.model, lang_from_iso3, lang_to_iso3, usage)
+                .await;
+        }
+
+        match parse_response(&completion.text) {
+            Ok((resp, repaired)) => {
+                span.record("repaired", repaired);
+                if 1 < attempt || repaired {
+                    info!(
+                        attempt,
+                        repaired, "recovered from malformed JSON from model"
+                    );
+                }
+                let stats = LlmCallStats {
+                    cached: false,
+                    attempts: attempt,
+                    repaired,
+                };
+                return Ok((resp, stats));
+            }
+            Err(e) => {
+                warn!(attempt, error = %e, sample = %truncate(&completion.text), "invalid JSON from model");
+                prompt = build_retry_prompt(&base_prompt, &completion.text, &e);
+                last_error = e;
+            }
+        }
+    }
+
+    error!(attempts = MAX_ATTEMPTS, error = %last_error, "giving up on invalid JSON from model");
+    Err(SourceError::Upstream(
+        StatusCode::BAD_GATEWAY,
+        format!("invalid JSON from ChatGPT: {last_error} (after {MAX_ATTEMPTS} attempts)"),
+    ))
+}
+
+/// Returns the parsed response and whether it had to be repaired.
+fn parse_response(raw: &str) -> Result<(ChatGPTLexicalResponse, bool), String> {
+    let json = extract_json_object(raw).unwrap_or_else(|| raw.trim().to_string());
+    let strict_error = match serde_json::from_str(&json) {
+        Ok(resp) => return Ok((resp, false)),
+        Err(e) => e.to_string(),
+    };
+
+    let repaired = repair_json(raw);
+    let json = extract_json_object(&repaired).unwrap_or_else(|| repaired.trim().to_string());
+    let mut value: Value = serde_json::from_str(&json).map_err(|_| strict_error.clone())?;
+    let defaults =
+        serde_json::to_value(ChatGPTLexicalResponse::default()).map_err(|e| e.to_string())?;
+    let added = fill_missing_fields(&mut value, &defaults);
+    if !added.is_empty() {
+        debug!(?added, "defaulted fields missing in model reply");
+    }
+    serde_json::from_value(value)
+        .map(|resp| (resp, true))
+        .map_err(|_| strict_error)
+}
 
+fn build_retry_prompt(prompt: &str, reply: &str, error: &str) -> String {
+    format!(
+        r#"{prompt}
+Your previous reply could not be parsed as the required JSON.
+Previous reply: {}
+Parse error: {error}
+Reply again, with valid JSON only.
+"#,
+        truncate(reply)
+    )
+}
+
+fn to_lexical_items(
+    resp: ChatGPTLexicalResponse,
+    query: &str,
+    lang_from_iso3: &str,
+    lang_to_iso3: &str,
+) -> Vec<LexicalItemDetail> {
     let source = "chatgpt".to_string();
     let mut out = Vec::<LexicalItemDetail>::new();
 
-    out.push(LexicalItemDetail::Forms(Forms {
-        text: resp.forms,
-        source: source.clone(),
-    }));
+    out.push(LexicalItemDetail::Forms(resp.forms.into_forms(&source)));
 
     out.push(LexicalItemDetail::Explanation(Explanation {
         text: resp.explanation,
@@ -54,6 +213,7 @@ pub async fn request(
     };
     out.push(LexicalItemDetail::WordTranslations(WordTranslations {
         translations_set,
+        translations_sources: None,
         source: source.clone(),
     }));
 
@@ -72,114 +232,278 @@ pub async fn request(
     }));
 
     for ex in resp.examples {
-        if let Some((lhs, rhs)) = ex.split_once('|') {
-            let examples_ts = TranslationsSet {
-                original: Sentence::new(lhs.trim(), lang_from_iso3, &source),
-                translations: vec![Sentence::new(rhs.trim(), lang_to_iso3, &source)],
-                translations_qualities: None,
-            };
-            out.push(LexicalItemDetail::Example(Example {
-                translations_set: examples_ts,
-                source: source.clone(),
-            }));
-        }
+        let examples_ts = TranslationsSet {
+            original: Sentence::new(ex.original.trim(), lang_from_iso3, &source),
+            translations: vec![Sentence::new(ex.translation.trim(), lang_to_iso3, &source)],
+            translations_qualities: None,
+        };
+        out.push(LexicalItemDetail::Example(Example {
+            translations_set: examples_ts,
+            source: source.clone(),
+        }));
     }
 
-    Ok(out)
+    out
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 struct ChatGPTLexicalResponse {
-    forms: String,
+    forms: ChatGPTForms,
     translations: Vec<String>,
     synonyms: Vec<String>,
     explanation: String,
-    examples: Vec<String>,
+    examples: Vec<ChatGPTExample>,
+}
+
+/// Structured forms; a bare string (the pre-structured format, still given
+/// by some models without schema support) is accepted as `text` only.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ChatGPTForms {
+    Structured {
+        text: String,
+        part_of_speech: Option<String>,
+        gender: Option<String>,
+        article: Option<String>,
+        plural: Option<String>,
+        #[serde(default)]
+        verb_principal_parts: Vec<String>,
+    },
+    Text(String),
+}
+
+impl Default for ChatGPTForms {
+    fn default() -> Self {
+        ChatGPTForms::Text(String::new())
+    }
+}
+
+impl ChatGPTForms {
+    fn into_forms(self, source: &str) -> Forms {
+        match self {
+            ChatGPTForms::Text(text) => Forms::new(text, source),
+            ChatGPTForms::Structured {
+                text,
+                part_of_speech,
+                gender,
+                article,
+                plural,
+                verb_principal_parts,
+            } => Forms {
+                text,
+                part_of_speech: part_of_speech.as_deref().map(PartOfSpeech::parse),
+                gender: gender.as_deref().and_then(GrammaticalGender::parse),
+                article: article.filter(|a| !a.trim().is_empty()),
+                plural: plural.filter(|p| !p.trim().is_empty()),
+                verb_principal_parts,
+                source: source.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatGPTExample {
+    original: String,
+    translation: String,
+}
+
+impl ChatGPTLexicalResponse {
+    /// Structured-outputs schema mirroring the struct above. Strict mode
+    /// requires every property to be listed in `required` and no extras.
+    fn response_format() -> JsonSchemaFormat {
+        let string_array = json!({ "type": "array", "items": { "type": "string" } });
+        let nullable_string = json!({ "type": ["string", "null"] });
+        let forms = json!({
+            "type": "object",
+            "properties": {
+                "text": { "type": "string" },
+                "part_of_speech": {
+                    "type": ["string", "null"],
+                    "enum": PARTS_OF_SPEECH.iter().copied().map(Some).chain([None]).collect::<Vec<_>>()
+                },
+                "gender": {
+                    "type": ["string", "null"],
+                    "enum": ["masculine", "feminine", "neuter", "common", null]
+                },
+                "article": nullable_string,
+                "plural": nullable_string,
+                "verb_principal_parts": string_array
+            },
+            "required": ["text", "part_of_speech", "gender", "article", "plural", "verb_principal_parts"],
+            "additionalProperties": false
+        });
+        let example = json!({
+            "type": "object",
+            "properties": {
+                "original": { "type": "string" },
+                "translation": { "type": "string" }
+            },
+            "required": ["original", "translation"],
+            "additionalProperties": false
+        });
+        let schema: Value = json!({
+            "type": "object",
+            "properties": {
+                "forms": forms,
+                "translations": string_array,
+                "synonyms": string_array,
+                "explanation": { "type": "string" },
+                "examples": { "type": "array", "items": example }
+            },
+            "required": ["forms", "translations", "synonyms", "explanation", "examples"],
+            "additionalProperties": false
+        });
+        JsonSchemaFormat {
+            name: "lexical_item",
+            schema,
+        }
+    }
 }
 
+const PARTS_OF_SPEECH: &[&str] = &[
+    "noun",
+    "proper_noun",
+    "verb",
+    "adjective",
+    "adverb",
+    "pronoun",
+    "preposition",
+    "conjunction",
+    "interjection",
+    "numeral",
+    "article",
+    "particle",
+    "phrase",
+    "other",
+];
+
 /// Prompt builder (adapted from your Android code).
-fn build_prompt(query: &str, lang_from_iso3: &str, lang_to_iso3: &str) -> String {
-    let forms_explanation = r#"
-if noun: article, singular form, plural form changes, e.g.:
-der Hund, -e
-der Platz, -äe
-der Wurm, -(ü)e
-if verb: follow next examples:
-gehen, geht, ging, ist gegangen
-lieben, liebt, liebte, hat geliebt
-for others (adverb, adjective) make it as simple as possible
-"#;
+/// `with_json_layout` spells the expected JSON out for providers which
+/// cannot enforce a schema themselves.
+fn build_prompt(
+    query: &str,
+    lang_from_iso3: &str,
+    lang_to_iso3: &str,
+    with_json_layout: bool,
+) -> String {
+    let forms_explanation = forms_template(lang_from_iso3);
+    let parts_of_speech = PARTS_OF_SPEECH.join(", ");
 
-    format!(
+    let json_layout = if with_json_layout {
         r#"
-You are called from a language learning app. Your goal is to reply with **JSON only**, no prose, no code fences.
-
-The JSON format must be exactly:
-{{
-  "forms": "<FORMS>",
+Reply with **JSON only**, no prose, no code fences. The JSON format must be exactly:
+{
+  "forms": {
+    "text": "<FORMS>",
+    "part_of_speech": "<PART_OF_SPEECH>",
+    "gender": "<GENDER>",
+    "article": "<ARTICLE>",
+    "plural": "<PLURAL>",
+    "verb_principal_parts": ["<PRINCIPAL_PART>", "<PRINCIPAL_PART>"]
+  },
   "translations": ["<TRANSLATION>", "<TRANSLATION>", "<TRANSLATION>"],
   "synonyms": ["<SYNONYM>", "<SYNONYM>", "<SYNONYM>"],
   "explanation": "<EXPLANATION_TARGET_LANG>",
   "examples": [
-    "<EXAMPLE>",
-    "<EXAMPLE>",
-    "<EXAMPLE>",
-    "<EXAMPLE>",
-    "<EXAMPLE>"
+    {"original": "<EXAMPLE_ORIGINAL>", "translation": "<EXAMPLE_TRANSLATION>"},
+    {"original": "<EXAMPLE_ORIGINAL>", "translation": "<EXAMPLE_TRANSLATION>"},
+    {"original": "<EXAMPLE_ORIGINAL>", "translation": "<EXAMPLE_TRANSLATION>"}
   ]
-}}
+}
+"#
+    } else {
+        ""
+    };
 
+    format!(
+        r#"
+You are called from a language learning app.
+{json_layout}
 Word to explain: {query}
 Source language (ISO-3): {lang_from_iso3}
 Target language (ISO-3): {lang_to_iso3}
 
-Placeholders:
-<FORMS>: {forms_explanation}
-<TRANSLATION>: a translation into lang {lang_to_iso3}
-<SYNONYM>: a synonym in lang {lang_from_iso3}
-<EXPLANATION_TARGET_LANG>: short (2-3 sentences) explanation of the word, in lang {lang_to_iso3}
-<EXAMPLE>: example sentence in lang {lang_from_iso3} | example sentence in lang {lang_to_iso3}
-The '|' is a required delimiter. Example sentences must be short. Translations and synonyms may contain 1-6 entries.
+Fields:
+<FORMS> (forms.text): forms of the word in lang {lang_from_iso3}, {forms_explanation}
+<PART_OF_SPEECH> (forms.part_of_speech): one of {parts_of_speech}
+<GENDER> (forms.gender): grammatical gender of a noun (masculine, feminine, neuter, common), null if not applicable
+<ARTICLE> (forms.article): definite singular article of a noun, null if the language has none or not a noun
+<PLURAL> (forms.plural): full plural form of a noun, null if not applicable
+<PRINCIPAL_PART> (forms.verb_principal_parts): the verb forms listed in forms.text, one per entry; empty if not a verb
+<TRANSLATION> (translations): a translation into lang {lang_to_iso3}
+<SYNONYM> (synonyms): a synonym in lang {lang_from_iso3}
+<EXPLANATION_TARGET_LANG> (explanation): short (2-3 sentences) explanation of the word, in lang {lang_to_iso3}
+<EXAMPLE_ORIGINAL> (examples[].original): example sentence in lang {lang_from_iso3}
+<EXAMPLE_TRANSLATION> (examples[].translation): the same sentence translated into lang {lang_to_iso3}
+Example sentences must be short, give up to 5 of them. Translations and synonyms may contain 1-6 entries.
 "#
     )
 }
 
 /// Try to recover a JSON object from a model reply, even if wrapped in prose or ``` fences.
-/// Returns `Some(json)` if we can locate `{ ... }`, otherwise `None`.
+/// Returns the first balanced `{ ... }` which parses as JSON, otherwise `None`.
 fn extract_json_object(s: &str) -> Option<String> {
-    let bytes = s.as_bytes();
-    let mut first = None;
-    let mut last = None;
-
-    for (i, &b) in bytes.iter().enumerate() {
-        if b == b'{' {
-            first = Some(i);
-            break;
+    s.match_indices('{').find_map(|(start, _)| {
+        let end = balanced_object_end(&s[start..])?;
+        let candidate = &s[start..start + end];
+        serde_json::from_str::<Value>(candidate)
+            .ok()
+            .map(|_| candidate.to_string())
+    })
+}
+
+/// Length of the `{ ... }` which `s` starts with, skipping braces inside strings.
+fn balanced_object_end(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
         }
-    }
-    for (i, &b) in bytes.iter().enumerate().rev() {
-        if b == b'}' {
-            last = Some(i);
-            break;
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
         }
     }
-    match (first, last) {
-        (Some(start), Some(end)) if end >= start => Some(s[start..=end].to_string()),
-        _ => None,
-    }
+    None
 }
 
 #[cfg(test)]
 mod tests {
     use axum::http::StatusCode;
-    use mockito::Server;
+    use mockito::{Matcher, Server};
     use reqwest::Client;
     use serde_json::json;
 
+    use crate::llm::chatgpt::ChatGPTProvider;
+    use crate::llm::fake_llm::FakeLlmProvider;
+    use crate::llm::lexical_cache::LexicalCache;
+    use crate::llm::usage_ledger::{LlmPricing, UsageLedger};
     use crate::model::{
         LexicalItemDetail, Sentence, TranslationsSet,
-        lexical_item_detail::{Example, Explanation, Forms, Synonyms, WordTranslations},
+        lexical_item_detail::{
+            Example, Explanation, Forms, GrammaticalGender, PartOfSpeech, Synonyms,
+            WordTranslations,
+        },
     };
+    use crate::upstream::source_error::SourceError;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::time::Duration;
 
     fn wrap_in_chatgpt_response(payload: &str) -> String {
         json!({
@@ -201,13 +525,20 @@ mod tests {
 
     const LEX_JSON: &str = r#"
     {
-      "forms": "der Hund, -e",
+      "forms": {
+        "text": "der Hund, -e",
+        "part_of_speech": "noun",
+        "gender": "masculine",
+        "article": "der",
+        "plural": "die Hunde",
+        "verb_principal_parts": []
+      },
       "translations": ["dog", "hound"],
       "synonyms": ["Hündin", "Köter"],
       "explanation": "Der Hund ist ein Haustier.",
       "examples": [
-        "Hund|Dog",
-        "Mein Hund|My dog"
+        {"original": "Hund", "translation": "Dog"},
+        {"original": "Mein Hund", "translation": "My dog"}
       ]
     }"#;
 
@@ -217,17 +548,98 @@ mod tests {
         lang_from_iso3: &str,
         lang_to_iso3: &str,
         url: Option<&str>,
-    ) -> Result<Vec<LexicalItemDetail>, (StatusCode, String)> {
-        super::request(
-            http_client,
-            "key",
-            query,
-            lang_from_iso3,
-            lang_to_iso3,
+    ) -> Result<Vec<LexicalItemDetail>, SourceError> {
+        let provider = ChatGPTProvider::new(
+            http_client.clone(),
+            "key".into(),
+            None,
+            url.map(str::to_string),
+        );
+        super::request(&provider, None, None, query, lang_from_iso3, lang_to_iso3)
+            .await
+            .map(|r| r.items)
+    }
+
+    #[tokio::test]
+    async fn works_with_any_provider() {
+        let provider = FakeLlmProvider::new(vec![LEX_JSON.to_string()]);
+
+        let items = super::request(&provider, None, None, "Hund", "deu", "eng")
+            .await
+            .expect("Ok")
+            .items;
+
+        assert_eq!(items.len(), 6);
+        let prompts = provider.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("Word to explain: Hund"));
+    }
+
+    #[tokio::test]
+    async fn usage_is_recorded_and_budget_enforced() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("POST", "/v1/responses")
+            .with_status(200)
+            .with_body(wrap_in_chatgpt_response(LEX_JSON))
+            .expect(1)
+            .create();
+        let provider = ChatGPTProvider::new(
+            Client::new(),
+            "key".into(),
             None,
-            url,
-        )
-        .await
+            Some(format!("{}/v1/responses", server.url())),
+        );
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let pricing = LlmPricing {
+            input_per_mtok: 1.0,
+            output_per_mtok: 1.0,
+        };
+        // The first call (30 tokens) spends the whole budget
+        let ledger = UsageLedger::new(pool, pricing, Some(0.00003))
+            .await
+            .unwrap();
+
+        super::request(&provider, None, Some(&ledger), "Hund", "deu", "eng")
+            .await
+            .expect("Ok");
+        let pairs = ledger.by_language_pair(1).await.unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].input_tokens, pairs[0].output_tokens), (10, 20));
+
+        let err = super::request(&provider, None, Some(&ledger), "Hund", "deu", "eng")
+            .await
+            .expect_err("Err");
+        assert!(matches!(err, SourceError::BudgetExceeded(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn second_request_is_served_from_cache() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let cache = LexicalCache::new(pool, Duration::from_secs(60))
+            .await
+            .unwrap();
+        let provider = FakeLlmProvider::new(vec![LEX_JSON.to_string()]);
+
+        let first = super::request(&provider, Some(&cache), None, "Hund", "deu", "eng")
+            .await
+            .expect("Ok");
+        let second = super::request(&provider, Some(&cache), None, " Hund ", "deu", "eng")
+            .await
+            .expect("Ok");
+
+        assert_eq!(first.items, second.items);
+        assert_eq!(first.stats.attempts, 1);
+        assert!(second.stats.cached);
+        assert_eq!(provider.prompts().len(), 1);
     }
 
     #[tokio::test]
@@ -280,6 +692,11 @@ mod tests {
             vec![
                 LexicalItemDetail::Forms(Forms {
                     text: "der Hund, -e".into(),
+                    part_of_speech: Some(PartOfSpeech::Noun),
+                    gender: Some(GrammaticalGender::Masculine),
+                    article: Some("der".into()),
+                    plural: Some("die Hunde".into()),
+                    verb_principal_parts: vec![],
                     source: source.clone(),
                 }),
                 LexicalItemDetail::Explanation(Explanation {
@@ -288,6 +705,7 @@ mod tests {
                 }),
                 LexicalItemDetail::WordTranslations(WordTranslations {
                     translations_set: wt,
+                    translations_sources: None,
                     source: source.clone(),
                 }),
                 LexicalItemDetail::Synonyms(Synonyms {
@@ -326,8 +744,8 @@ mod tests {
             .await
             .expect_err("Err");
 
-        assert_eq!(err.0, StatusCode::BAD_GATEWAY);
-        assert!(err.1.starts_with("invalid JSON from ChatGPT:"));
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+        assert!(err.message().starts_with("invalid JSON from ChatGPT:"));
     }
 
     #[tokio::test]
@@ -351,6 +769,149 @@ mod tests {
         assert_ne!(Vec::<LexicalItemDetail>::new(), items);
     }
 
+    #[tokio::test]
+    async fn schema_is_requested_from_structured_outputs_providers() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("POST", "/v1/responses")
+            .match_body(Matcher::PartialJson(json!({
+                "text": { "format": { "type": "json_schema", "name": "lexical_item" } }
+            })))
+            .with_status(200)
+            .with_body(wrap_in_chatgpt_response(LEX_JSON))
+            .create();
+
+        let client = Client::new();
+        let url = format!("{}/v1/responses", server.url());
+        request_lexical(&client, "Hund", "deu", "eng", Some(&url))
+            .await
+            .expect("Ok");
+    }
+
+    #[tokio::test]
+    async fn json_layout_is_in_prompt_for_other_providers() {
+        let provider = FakeLlmProvider::new(vec![LEX_JSON.to_string()]);
+        super::request(&provider, None, None, "Hund", "deu", "eng")
+            .await
+            .expect("Ok");
+        assert!(provider.prompts()[0].contains("Reply with **JSON only**"));
+    }
+
+    #[tokio::test]
+    async fn forms_description_follows_source_language() {
+        let provider = FakeLlmProvider::new(vec![LEX_JSON.to_string()]);
+        super::request(&provider, None, None, "chien", "fra", "eng")
+            .await
+            .expect("Ok");
+        let prompt = &provider.prompts()[0];
+        assert!(prompt.contains("le cheval, -aux"));
+        assert!(!prompt.contains("der Hund, -e"));
+    }
+
+    #[test]
+    fn json_is_extracted_from_prose_with_braces() {
+        let reply = r#"Sure {here it is}: {"a": "}{", "b": {"c": 1}} Hope {that} helps."#;
+        assert_eq!(
+            super::extract_json_object(reply).as_deref(),
+            Some(r#"{"a": "}{", "b": {"c": 1}}"#)
+        );
+        assert_eq!(super::extract_json_object("no json {here"), None);
+    }
+
+    #[tokio::test]
+    async fn verb_forms_are_structured() {
+        let answer = json!({
+            "forms": {
+                "text": "gehen, geht, ging, ist gegangen",
+                "part_of_speech": "verb",
+                "gender": null,
+                "article": null,
+                "plural": null,
+                "verb_principal_parts": ["gehen", "geht", "ging", "ist gegangen"]
+            },
+            "translations": ["go"],
+            "synonyms": [],
+            "explanation": "To move.",
+            "examples": []
+        });
+        let provider = FakeLlmProvider::new(vec![answer.to_string()]);
+
+        let result = super::request(&provider, None, None, "gehen", "deu", "eng")
+            .await
+            .expect("Ok");
+
+        assert_eq!(
+            result.items[0],
+            LexicalItemDetail::Forms(Forms {
+                text: "gehen, geht, ging, ist gegangen".into(),
+                part_of_speech: Some(PartOfSpeech::Verb),
+                gender: None,
+                article: None,
+                plural: None,
+                verb_principal_parts: vec![
+                    "gehen".into(),
+                    "geht".into(),
+                    "ging".into(),
+                    "ist gegangen".into()
+                ],
+                source: "chatgpt".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn schema_lists_all_response_fields() {
+        let format = super::ChatGPTLexicalResponse::response_format();
+        let sample: serde_json::Value = serde_json::from_str(LEX_JSON).unwrap();
+        let mut fields: Vec<_> = sample.as_object().unwrap().keys().cloned().collect();
+        fields.sort();
+        let mut required: Vec<String> =
+            serde_json::from_value(format.schema["required"].clone()).unwrap();
+        required.sort();
+        assert_eq!(fields, required);
+    }
+
+    #[tokio::test]
+    async fn locally_repairable_json_is_not_asked_again() {
+        let broken =
+            "{ “forms”: “der Hund, -e”, \"translations\": [\"dog\",], \"explanation\": \"\" }";
+        let provider = FakeLlmProvider::new(vec![broken.to_string()]);
+
+        let result = super::request(&provider, None, None, "Hund", "deu", "eng")
+            .await
+            .expect("Ok");
+
+        assert_eq!(provider.prompts().len(), 1);
+        assert_eq!(
+            result.stats,
+            super::LlmCallStats {
+                cached: false,
+                attempts: 1,
+                repaired: true,
+            }
+        );
+        assert_eq!(
+            result.items[0],
+            LexicalItemDetail::Forms(Forms::new("der Hund, -e", "chatgpt"))
+        );
+    }
+
+    #[tokio::test]
+    async fn model_is_asked_again_with_parse_error() {
+        let provider = FakeLlmProvider::new(vec!["{ error }".to_string(), LEX_JSON.to_string()]);
+
+        let result = super::request(&provider, None, None, "Hund", "deu", "eng")
+            .await
+            .expect("Ok");
+
+        assert_eq!(result.stats.attempts, 2);
+        assert!(!result.stats.repaired);
+        let prompts = provider.prompts();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[1].contains("Previous reply: { error }"));
+        assert!(prompts[1].contains("Parse error: "));
+    }
+
     #[tokio::test]
     async fn upstream_non_2xx_bubbles_as_error() {
         let mut server = Server::new_async().await;
@@ -368,7 +929,7 @@ mod tests {
             .await
             .expect_err("Err");
 
-        assert_eq!(err.0, StatusCode::BAD_GATEWAY);
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
     }
 
     #[tokio::test]
@@ -383,6 +944,6 @@ mod tests {
             .await
             .expect_err("Err");
 
-        assert_eq!(err.0, StatusCode::BAD_GATEWAY);
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
     }
 }
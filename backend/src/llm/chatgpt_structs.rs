@@ -1,9 +1,26 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Serialize)]
 pub struct ChatGPTRequest<'a> {
     pub model: &'a str,
     pub input: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<ChatGPTTextConfig<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct ChatGPTTextConfig<'a> {
+    pub format: ChatGPTTextFormat<'a>,
+}
+
+/// Structured outputs: `{"type": "json_schema", "name": ..., "schema": ..., "strict": true}`
+#[derive(Serialize)]
+pub struct ChatGPTTextFormat<'a> {
+    pub r#type: &'a str,
+    pub name: &'a str,
+    pub schema: &'a Value,
+    pub strict: bool,
 }
 
 #[allow(dead_code)]
@@ -33,10 +50,10 @@ pub struct ChatGPTContent {
     pub r#type: String,
 }
 
-#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct ChatGPTUsage {
     pub input_tokens: i32,
     pub output_tokens: i32,
+    #[allow(dead_code)]
     pub total_tokens: i32,
 }
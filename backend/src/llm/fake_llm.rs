@@ -0,0 +1,80 @@
+use super::llm_provider::{JsonSchemaFormat, LlmCompletion, LlmProvider};
+use crate::upstream::source_error::SourceError;
+use async_trait::async_trait;
+use std::sync::Mutex;
+
+/// Canned answer in the format `chatgpt_lexical_items` asks the model for.
+const DEFAULT_ANSWER: &str = r#"{
+  "forms": {
+    "text": "der Hund, -e",
+    "part_of_speech": "noun",
+    "gender": "masculine",
+    "article": "der",
+    "plural": "die Hunde",
+    "verb_principal_parts": []
+  },
+  "translations": ["dog", "hound"],
+  "synonyms": ["Köter"],
+  "explanation": "A domesticated carnivorous mammal.",
+  "examples": [{"original": "Der Hund bellt.", "translation": "The dog barks."}]
+}"#;
+
+/// Offline [`LlmProvider`] replying with predefined answers, one per call.
+/// Once the answers run out, the last one is repeated.
+pub struct FakeLlmProvider {
+    answers: Mutex<Vec<String>>,
+    /// Only recorded in tests: `--llm-provider fake` servers run for long.
+    #[cfg(test)]
+    prompts: Mutex<Vec<String>>,
+}
+
+impl FakeLlmProvider {
+    pub fn new(answers: Vec<String>) -> Self {
+        assert!(
+            !answers.is_empty(),
+            "FakeLlmProvider needs at least one answer"
+        );
+        let mut answers = answers;
+        answers.reverse();
+        Self {
+            answers: Mutex::new(answers),
+            #[cfg(test)]
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Prompts received so far, in order.
+    #[cfg(test)]
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().unwrap().clone()
+    }
+}
+
+impl Default for FakeLlmProvider {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_ANSWER.to_string()])
+    }
+}
+
+#[async_trait]
+impl LlmProvider for FakeLlmProvider {
+    async fn complete(
+        &self,
+        #[cfg_attr(not(test), allow(unused_variables))] prompt: &str,
+        _format: Option<&JsonSchemaFormat>,
+    ) -> Result<LlmCompletion, SourceError> {
+        #[cfg(test)]
+        self.prompts.lock().unwrap().push(prompt.to_string());
+        let mut answers = self.answers.lock().unwrap();
+        let answer = if answers.len() > 1 {
+            answers.pop().unwrap()
+        } else {
+            answers[0].clone()
+        };
+        Ok(LlmCompletion {
+            text: answer,
+            model: "fake".to_string(),
+            usage: None,
+        })
+    }
+}
@@ -0,0 +1,50 @@
+/// Version of the per-language descriptions of the `forms` prompt field,
+/// which live in `prompts/forms/v<VERSION>/<iso3>.txt`. Edit them by copying
+/// the directory to the next version and bumping both this and the path in
+/// `template!`, so that cached answers to old prompts expire.
+pub const VERSION: i64 = 1;
+
+macro_rules! template {
+    ($name:literal) => {
+        include_str!(concat!("../../prompts/forms/v1/", $name, ".txt"))
+    };
+}
+
+const TEMPLATES: &[(&str, &str)] = &[
+    ("deu", template!("deu")),
+    ("eng", template!("eng")),
+    ("fra", template!("fra")),
+    ("rus", template!("rus")),
+    ("spa", template!("spa")),
+];
+
+const DEFAULT_TEMPLATE: &str = template!("default");
+
+/// Describes which forms to list for a word in `lang_iso3`.
+pub fn forms_template(lang_iso3: &str) -> &'static str {
+    TEMPLATES
+        .iter()
+        .find(|(lang, _)| *lang == lang_iso3)
+        .map(|(_, template)| *template)
+        .unwrap_or(DEFAULT_TEMPLATE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_languages_have_own_templates() {
+        assert!(forms_template("deu").contains("der Hund, -e"));
+        assert!(forms_template("fra").contains("le cheval, -aux"));
+        assert!(forms_template("rus").contains("несов."));
+        assert!(forms_template("eng").contains("go, went, gone"));
+        assert!(forms_template("spa").contains("tener, tengo, tuve, tenido"));
+    }
+
+    #[test]
+    fn unknown_languages_get_generic_template() {
+        assert_eq!(forms_template("fin"), DEFAULT_TEMPLATE);
+        assert!(!DEFAULT_TEMPLATE.contains("der Hund"));
+    }
+}
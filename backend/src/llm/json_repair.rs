@@ -0,0 +1,123 @@
+use serde_json::Value;
+
+/// Fixes the most common ways LLMs break JSON syntax:
+/// typographic quotes used as string delimiters and trailing commas.
+/// Typographic quotes inside regular strings are left untouched.
+pub fn repair_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    // Delimiter of the string we are in, if any
+    let mut string_delim: Option<char> = None;
+    let mut escaped = false;
+    let mut pending_comma: Option<String> = None;
+
+    for c in s.chars() {
+        if let Some(delim) = string_delim {
+            if escaped {
+                escaped = false;
+                out.push(c);
+            } else if c == '\\' {
+                escaped = true;
+                out.push(c);
+            } else if (c == '"' && delim == '"') || (delim != '"' && is_smart_quote(c)) {
+                string_delim = None;
+                out.push('"');
+            } else if c == '"' {
+                // An ASCII quote inside a smart-quoted string
+                out.push_str("\\\"");
+            } else {
+                out.push(c);
+            }
+            continue;
+        }
+
+        if let Some(comma) = pending_comma.as_mut() {
+            if c.is_whitespace() {
+                comma.push(c);
+                continue;
+            }
+            let comma = pending_comma.take().unwrap();
+            if c == '}' || c == ']' {
+                // Trailing comma: drop it, keep the whitespace
+                out.push_str(&comma[1..]);
+            } else {
+                out.push_str(&comma);
+            }
+        }
+
+        match c {
+            ',' => pending_comma = Some(c.to_string()),
+            '"' => {
+                string_delim = Some('"');
+                out.push('"');
+            }
+            _ if is_smart_quote(c) => {
+                string_delim = Some(c);
+                out.push('"');
+            }
+            _ => out.push(c),
+        }
+    }
+    if let Some(comma) = pending_comma {
+        out.push_str(&comma);
+    }
+    out
+}
+
+fn is_smart_quote(c: char) -> bool {
+    matches!(c, '“' | '”' | '„' | '‟' | '«' | '»')
+}
+
+/// Adds the top-level fields of `defaults` which are missing in `value`.
+/// Returns the names of the added fields.
+pub fn fill_missing_fields(value: &mut Value, defaults: &Value) -> Vec<String> {
+    let (Some(obj), Some(defaults)) = (value.as_object_mut(), defaults.as_object()) else {
+        return Vec::new();
+    };
+    let mut added = Vec::new();
+    for (key, default) in defaults {
+        if !obj.contains_key(key) {
+            obj.insert(key.clone(), default.clone());
+            added.push(key.clone());
+        }
+    }
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(s: &str) -> Value {
+        serde_json::from_str(&repair_json(s)).expect("repaired JSON must parse")
+    }
+
+    #[test]
+    fn trailing_commas_are_removed() {
+        assert_eq!(
+            parse("{\"a\": [1, 2, ],\n \"b\": {\"c\": 3,}, }"),
+            json!({ "a": [1, 2], "b": { "c": 3 } })
+        );
+    }
+
+    #[test]
+    fn commas_inside_strings_are_kept() {
+        assert_eq!(parse(r#"{"a": "x, }"}"#), json!({ "a": "x, }" }));
+    }
+
+    #[test]
+    fn smart_quote_delimiters_become_ascii() {
+        assert_eq!(
+            parse(r#"{“forms”: „der Hund, -e“, "q": "ein „Hund“"}"#),
+            json!({ "forms": "der Hund, -e", "q": "ein „Hund“" })
+        );
+    }
+
+    #[test]
+    fn missing_fields_are_defaulted() {
+        let mut value = json!({ "a": 1 });
+        let added = fill_missing_fields(&mut value, &json!({ "a": 0, "b": [] }));
+        assert_eq!(value, json!({ "a": 1, "b": [] }));
+        assert_eq!(added, vec!["b".to_string()]);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::util::unix_now;
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tracing::warn;
+
+/// Persistent cache of parsed LLM lexical responses, keyed by
+/// `(query, lang_from_iso3, lang_to_iso3)`.
+///
+/// Entries written with another prompt version or older than the TTL are
+/// treated as missing. Cache failures are logged and never fail a lookup.
+#[derive(Clone)]
+pub struct LexicalCache {
+    pool: SqlitePool,
+    ttl: Duration,
+}
+
+impl LexicalCache {
+    pub async fn new(pool: SqlitePool, ttl: Duration) -> Result<Self, sqlx::Error> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS llm_lexical_cache (
+                query          TEXT    NOT NULL,
+                lang_from      TEXT    NOT NULL,
+                lang_to        TEXT    NOT NULL,
+                prompt_version INTEGER NOT NULL,
+                payload        TEXT    NOT NULL,
+                created_at     INTEGER NOT NULL,
+                PRIMARY KEY (query, lang_from, lang_to)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        Ok(Self { pool, ttl })
+    }
+
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        lang_from_iso3: &str,
+        lang_to_iso3: &str,
+        prompt_version: i64,
+    ) -> Option<T> {
+        let min_created_at = unix_now() - self.ttl.as_secs() as i64;
+        let row: Option<(String,)> = sqlx::query_as(
+            r#"
+            SELECT payload FROM llm_lexical_cache
+            WHERE query = ?1 AND lang_from = ?2 AND lang_to = ?3
+              AND prompt_version = ?4
+              AND created_at >= ?5
+            "#,
+        )
+        .bind(query)
+        .bind(lang_from_iso3)
+        .bind(lang_to_iso3)
+        .bind(prompt_version)
+        .bind(min_created_at)
+        .fetch_optional(&self.pool)
+        .await
+        .inspect_err(|e| warn!(error = %e, "failed to read LLM cache"))
+        .ok()?;
+
+        let (payload,) = row?;
+        serde_json::from_str(&payload)
+            .inspect_err(|e| warn!(error = %e, "dropping undecodable LLM cache entry"))
+            .ok()
+    }
+
+    pub async fn put<T: Serialize>(
+        &self,
+        query: &str,
+        lang_from_iso3: &str,
+        lang_to_iso3: &str,
+        prompt_version: i64,
+        value: &T,
+    ) {
+        let payload = match serde_json::to_string(value) {
+            Ok(p) => p,
+            Err(e) => {
+                warn!(error = %e, "failed to encode LLM cache entry");
+                return;
+            }
+        };
+        let res = sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO llm_lexical_cache
+                (query, lang_from, lang_to, prompt_version, payload, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(query)
+        .bind(lang_from_iso3)
+        .bind(lang_to_iso3)
+        .bind(prompt_version)
+        .bind(payload)
+        .bind(unix_now())
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = res {
+            warn!(error = %e, "failed to write LLM cache");
+        }
+    }
+
+    /// Deletes entries matching all given filters (`None` matches anything)
+    /// and returns how many were removed.
+    pub async fn purge(
+        &self,
+        query: Option<&str>,
+        lang_from_iso3: Option<&str>,
+        lang_to_iso3: Option<&str>,
+    ) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM llm_lexical_cache
+            WHERE (?1 IS NULL OR query = ?1)
+              AND (?2 IS NULL OR lang_from = ?2)
+              AND (?3 IS NULL OR lang_to = ?3)
+            "#,
+        )
+        .bind(query)
+        .bind(lang_from_iso3)
+        .bind(lang_to_iso3)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    /// Deletes entries which can never be served again: written by another
+    /// prompt version or already expired.
+    pub async fn purge_stale(&self, prompt_version: i64) -> Result<u64, sqlx::Error> {
+        let min_created_at = unix_now() - self.ttl.as_secs() as i64;
+        let res = sqlx::query(
+            "DELETE FROM llm_lexical_cache WHERE prompt_version <> ?1 OR created_at < ?2",
+        )
+        .bind(prompt_version)
+        .bind(min_created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn new_cache(ttl: Duration) -> LexicalCache {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("connect :memory:");
+        LexicalCache::new(pool, ttl).await.expect("cache")
+    }
+
+    #[tokio::test]
+    async fn roundtrip_and_prompt_version_invalidation() {
+        let cache = new_cache(Duration::from_secs(60)).await;
+        cache.put("Hund", "deu", "eng", 1, &vec!["dog"]).await;
+
+        let hit: Option<Vec<String>> = cache.get("Hund", "deu", "eng", 1).await;
+        assert_eq!(hit, Some(vec!["dog".to_string()]));
+
+        let other_pair: Option<Vec<String>> = cache.get("Hund", "deu", "fra", 1).await;
+        assert_eq!(other_pair, None);
+
+        let new_prompt: Option<Vec<String>> = cache.get("Hund", "deu", "eng", 2).await;
+        assert_eq!(new_prompt, None);
+        assert_eq!(cache.purge_stale(2).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_misses() {
+        let cache = new_cache(Duration::ZERO).await;
+        sqlx::query(
+            "INSERT INTO llm_lexical_cache VALUES ('Hund', 'deu', 'eng', 1, '[\"dog\"]', 0)",
+        )
+        .execute(&cache.pool)
+        .await
+        .unwrap();
+
+        let hit: Option<Vec<String>> = cache.get("Hund", "deu", "eng", 1).await;
+        assert_eq!(hit, None);
+    }
+
+    #[tokio::test]
+    async fn purge_by_filters() {
+        let cache = new_cache(Duration::from_secs(60)).await;
+        cache.put("Hund", "deu", "eng", 1, &1).await;
+        cache.put("Hund", "deu", "fra", 1, &2).await;
+        cache.put("Katze", "deu", "eng", 1, &3).await;
+
+        assert_eq!(cache.purge(Some("Hund"), None, None).await.unwrap(), 2);
+        assert_eq!(cache.purge(None, None, None).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+use super::chatgpt::ChatGPTProvider;
+use super::fake_llm::FakeLlmProvider;
+use super::openai_compatible::OpenAiCompatibleProvider;
+use crate::upstream::circuit_breaker::CircuitBreaker;
+use crate::upstream::source_error::SourceError;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// A text-in/text-out LLM backend.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Sends `prompt` to the model and returns its plain-text answer.
+    /// `format` is only honoured when [`LlmProvider::supports_json_schema`]
+    /// is true; other providers ignore it.
+    async fn complete(
+        &self,
+        prompt: &str,
+        format: Option<&JsonSchemaFormat>,
+    ) -> Result<LlmCompletion, SourceError>;
+
+    /// Whether the provider can force its answer to match a JSON schema
+    /// (structured outputs). Callers must otherwise describe the expected
+    /// JSON in the prompt and be ready for prose around it.
+    fn supports_json_schema(&self) -> bool {
+        false
+    }
+}
+
+/// A named JSON schema the model's answer must conform to.
+#[derive(Clone, Debug)]
+pub struct JsonSchemaFormat {
+    pub name: &'static str,
+    pub schema: Value,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LlmCompletion {
+    pub text: String,
+    /// Model which actually answered, as reported by the provider.
+    pub model: String,
+    /// `None` when the provider does not report token usage.
+    pub usage: Option<LlmUsage>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LlmUsage {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+/// Which [`LlmProvider`] to use and how to reach it.
+pub enum LlmConfig {
+    /// OpenAI Responses API.
+    OpenAi {
+        api_key: String,
+        model: Option<String>,
+        url: Option<String>,
+    },
+    /// Any server exposing an OpenAI-style `/v1/chat/completions` endpoint
+    /// (Ollama, llama.cpp, vLLM, ...).
+    OpenAiCompatible {
+        url: String,
+        model: String,
+        api_key: Option<String>,
+        /// Whether the server understands `response_format: json_schema`.
+        structured_outputs: bool,
+    },
+    /// Deterministic canned answers, no network at all.
+    Fake,
+}
+
+impl LlmConfig {
+    /// Requests of networked providers go through `breaker`.
+    pub fn build(self, http_client: &Client, breaker: CircuitBreaker) -> Arc<dyn LlmProvider> {
+        let provider: Arc<dyn LlmProvider> = match self {
+            LlmConfig::OpenAi {
+                api_key,
+                model,
+                url,
+            } => Arc::new(ChatGPTProvider::new(
+                http_client.clone(),
+                api_key,
+                model,
+                url,
+            )),
+            LlmConfig::OpenAiCompatible {
+                url,
+                model,
+                api_key,
+                structured_outputs,
+            } => Arc::new(OpenAiCompatibleProvider::new(
+                http_client.clone(),
+                url,
+                model,
+                api_key,
+                structured_outputs,
+            )),
+            LlmConfig::Fake => return Arc::new(FakeLlmProvider::default()),
+        };
+        Arc::new(BreakerProvider { provider, breaker })
+    }
+}
+
+/// Fails fast while the provider's circuit is open.
+struct BreakerProvider {
+    provider: Arc<dyn LlmProvider>,
+    breaker: CircuitBreaker,
+}
+
+#[async_trait]
+impl LlmProvider for BreakerProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        format: Option<&JsonSchemaFormat>,
+    ) -> Result<LlmCompletion, SourceError> {
+        self.breaker
+            .call(self.provider.complete(prompt, format))
+            .await
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        self.provider.supports_json_schema()
+    }
+}
@@ -1,3 +1,10 @@
 mod chatgpt;
 pub(crate) mod chatgpt_lexical_items;
 mod chatgpt_structs;
+mod fake_llm;
+mod forms_templates;
+mod json_repair;
+pub(crate) mod lexical_cache;
+pub(crate) mod llm_provider;
+mod openai_compatible;
+pub(crate) mod usage_ledger;
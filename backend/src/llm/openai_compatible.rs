@@ -0,0 +1,263 @@
+use super::llm_provider::{JsonSchemaFormat, LlmCompletion, LlmProvider, LlmUsage};
+use crate::upstream::source_error::SourceError;
+use crate::util::truncate;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
+
+/// [`LlmProvider`] for servers implementing the OpenAI chat completions API,
+/// e.g. Ollama or llama.cpp at `http://localhost:11434/v1/chat/completions`.
+pub struct OpenAiCompatibleProvider {
+    http_client: Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    structured_outputs: bool,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(
+        http_client: Client,
+        url: String,
+        model: String,
+        api_key: Option<String>,
+        structured_outputs: bool,
+    ) -> Self {
+        Self {
+            http_client,
+            url,
+            model,
+            api_key,
+            structured_outputs,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatibleProvider {
+    async fn complete(
+        &self,
+        prompt: &str,
+        format: Option<&JsonSchemaFormat>,
+    ) -> Result<LlmCompletion, SourceError> {
+        let prompt = prompt.trim();
+        let response_format = format
+            .filter(|_| self.structured_outputs)
+            .map(|f| ResponseFormat {
+                r#type: "json_schema",
+                json_schema: ResponseJsonSchema {
+                    name: f.name,
+                    schema: &f.schema,
+                    strict: true,
+                },
+            });
+        let request_body = ChatCompletionRequest {
+            model: &self.model,
+            messages: vec![ChatMessageRequest {
+                role: "user",
+                content: prompt,
+            }],
+            response_format,
+        };
+
+        let mut req = self.http_client.post(&self.url).json(&request_body);
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let res = req.send().await.map_err(|e| {
+            error!(error = %e, url = %self.url, "network error talking to LLM server");
+            SourceError::Upstream(StatusCode::BAD_GATEWAY, e.to_string())
+        })?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let body = res.text().await.unwrap_or_default();
+            error!(%status, body = %truncate(&body), "LLM server non-success");
+            return Err(SourceError::Upstream(status, body));
+        }
+
+        let parsed: ChatCompletionResponse = res.json().await.map_err(|e| {
+            error!(error = %e, "failed to deserialize LLM server response");
+            SourceError::Upstream(StatusCode::BAD_GATEWAY, e.to_string())
+        })?;
+
+        let text = parsed
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content)
+            .ok_or_else(|| {
+                error!("missing choices[0].message.content in LLM server response");
+                SourceError::Upstream(
+                    StatusCode::BAD_GATEWAY,
+                    "missing `choices[0].message.content` in upstream response".to_string(),
+                )
+            })?;
+
+        Ok(LlmCompletion {
+            text,
+            model: parsed.model.unwrap_or_else(|| self.model.clone()),
+            usage: parsed.usage.map(|u| LlmUsage {
+                input_tokens: u.prompt_tokens,
+                output_tokens: u.completion_tokens,
+            }),
+        })
+    }
+
+    fn supports_json_schema(&self) -> bool {
+        self.structured_outputs
+    }
+}
+
+#[derive(Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessageRequest<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat<'a>>,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat<'a> {
+    r#type: &'a str,
+    json_schema: ResponseJsonSchema<'a>,
+}
+
+#[derive(Serialize)]
+struct ResponseJsonSchema<'a> {
+    name: &'a str,
+    schema: &'a Value,
+    strict: bool,
+}
+
+#[derive(Serialize)]
+struct ChatMessageRequest<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatChoice>,
+    model: Option<String>,
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatUsage {
+    prompt_tokens: i64,
+    completion_tokens: i64,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessageResponse,
+}
+
+#[derive(Deserialize)]
+struct ChatMessageResponse {
+    content: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn returns_first_choice() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("POST", "/v1/chat/completions")
+            .match_body(Matcher::Json(json!({
+                "model": "llama3",
+                "messages": [{ "role": "user", "content": "Hi?" }]
+            })))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "model": "llama3",
+                    "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hello!" } }],
+                    "usage": { "prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5 }
+                })
+                .to_string(),
+            )
+            .create();
+
+        let provider = OpenAiCompatibleProvider::new(
+            Client::new(),
+            format!("{}/v1/chat/completions", server.url()),
+            "llama3".into(),
+            None,
+            false,
+        );
+        let format = JsonSchemaFormat {
+            name: "ignored",
+            schema: json!({}),
+        };
+        let completion = provider.complete(" Hi? ", Some(&format)).await.unwrap();
+        assert_eq!(completion.text, "Hello!");
+        assert_eq!(
+            completion.usage,
+            Some(LlmUsage {
+                input_tokens: 3,
+                output_tokens: 2,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_response_format_when_supported() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("POST", "/v1/chat/completions")
+            .match_body(Matcher::PartialJson(json!({
+                "response_format": {
+                    "type": "json_schema",
+                    "json_schema": { "name": "answer", "schema": { "type": "object" }, "strict": true }
+                }
+            })))
+            .with_status(200)
+            .with_body(r#"{"choices": [{"message": {"content": "{}"}}]}"#)
+            .create();
+
+        let provider = OpenAiCompatibleProvider::new(
+            Client::new(),
+            format!("{}/v1/chat/completions", server.url()),
+            "llama3".into(),
+            None,
+            true,
+        );
+        let format = JsonSchemaFormat {
+            name: "answer",
+            schema: json!({ "type": "object" }),
+        };
+        let completion = provider.complete("Hi?", Some(&format)).await.unwrap();
+        assert_eq!(completion.text, "{}");
+        assert_eq!(completion.model, "llama3");
+    }
+
+    #[tokio::test]
+    async fn empty_choices_is_bad_gateway() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("POST", "/v1/chat/completions")
+            .with_status(200)
+            .with_body(r#"{"choices": []}"#)
+            .create();
+
+        let provider = OpenAiCompatibleProvider::new(
+            Client::new(),
+            format!("{}/v1/chat/completions", server.url()),
+            "llama3".into(),
+            Some("secret".into()),
+            false,
+        );
+        let err = provider.complete("Hi?", None).await.expect_err("Err");
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+    }
+}
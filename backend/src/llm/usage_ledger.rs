@@ -0,0 +1,257 @@
+use super::llm_provider::LlmUsage;
+use crate::model::{LlmDailyUsage, LlmLanguagePairUsage};
+use crate::util::unix_now;
+use sqlx::SqlitePool;
+use tracing::warn;
+
+/// USD per 1M tokens.
+#[derive(Clone, Copy, Debug)]
+pub struct LlmPricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl LlmPricing {
+    pub fn cost_usd(&self, usage: &LlmUsage) -> f64 {
+        (usage.input_tokens as f64 * self.input_per_mtok
+            + usage.output_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Records token usage and estimated cost of every LLM call.
+#[derive(Clone)]
+pub struct UsageLedger {
+    pool: SqlitePool,
+    pricing: LlmPricing,
+    daily_budget_usd: Option<f64>,
+}
+
+impl UsageLedger {
+    pub async fn new(
+        pool: SqlitePool,
+        pricing: LlmPricing,
+        daily_budget_usd: Option<f64>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS llm_usage (
+                id            INTEGER PRIMARY KEY,
+                created_at    INTEGER NOT NULL,
+                model         TEXT    NOT NULL,
+                lang_from     TEXT    NOT NULL,
+                lang_to       TEXT    NOT NULL,
+                input_tokens  INTEGER NOT NULL,
+                output_tokens INTEGER NOT NULL,
+                cost_usd      REAL    NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS llm_usage_created_at ON llm_usage (created_at)")
+            .execute(&pool)
+            .await?;
+        Ok(Self {
+            pool,
+            pricing,
+            daily_budget_usd,
+        })
+    }
+
+    pub fn daily_budget_usd(&self) -> Option<f64> {
+        self.daily_budget_usd
+    }
+
+    /// Failures are logged only: losing a ledger row must not fail a lookup.
+    pub async fn record(
+        &self,
+        model: &str,
+        lang_from_iso3: &str,
+        lang_to_iso3: &str,
+        usage: &LlmUsage,
+    ) {
+        let res = sqlx::query(
+            r#"
+            INSERT INTO llm_usage
+                (created_at, model, lang_from, lang_to, input_tokens, output_tokens, cost_usd)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+        )
+        .bind(unix_now())
+        .bind(model)
+        .bind(lang_from_iso3)
+        .bind(lang_to_iso3)
+        .bind(usage.input_tokens)
+        .bind(usage.output_tokens)
+        .bind(self.pricing.cost_usd(usage))
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = res {
+            warn!(error = %e, "failed to record LLM usage");
+        }
+    }
+
+    /// Estimated spending since the start of the current UTC day.
+    pub async fn spent_today_usd(&self) -> Result<f64, sqlx::Error> {
+        let (spent,): (f64,) = sqlx::query_as(
+            r#"
+            SELECT COALESCE(SUM(cost_usd), 0.0) FROM llm_usage
+            WHERE created_at >= CAST(strftime('%s', date('now')) AS INTEGER)
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(spent)
+    }
+
+    pub async fn budget_exceeded(&self) -> bool {
+        let Some(budget) = self.daily_budget_usd else {
+            return false;
+        };
+        match self.spent_today_usd().await {
+            Ok(spent) => spent >= budget,
+            Err(e) => {
+                warn!(error = %e, "failed to read LLM spending, assuming budget is fine");
+                false
+            }
+        }
+    }
+
+    /// Per-UTC-day totals over the last `days` days, newest first.
+    pub async fn daily(&self, days: u32) -> Result<Vec<LlmDailyUsage>, sqlx::Error> {
+        let rows: Vec<(String, i64, i64, i64, f64)> = sqlx::query_as(
+            r#"
+            SELECT date(created_at, 'unixepoch') AS day,
+                   COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(cost_usd)
+            FROM llm_usage
+            WHERE created_at >= ?1
+            GROUP BY day
+            ORDER BY day DESC
+            "#,
+        )
+        .bind(since(days))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(day, calls, input_tokens, output_tokens, cost_usd)| LlmDailyUsage {
+                    day,
+                    calls,
+                    input_tokens,
+                    output_tokens,
+                    cost_usd,
+                },
+            )
+            .collect())
+    }
+
+    /// Per-language-pair totals over the last `days` days, most expensive first.
+    pub async fn by_language_pair(
+        &self,
+        days: u32,
+    ) -> Result<Vec<LlmLanguagePairUsage>, sqlx::Error> {
+        let rows: Vec<(String, String, i64, i64, i64, f64)> = sqlx::query_as(
+            r#"
+            SELECT lang_from, lang_to,
+                   COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(cost_usd) AS cost
+            FROM llm_usage
+            WHERE created_at >= ?1
+            GROUP BY lang_from, lang_to
+            ORDER BY cost DESC, lang_from, lang_to
+            "#,
+        )
+        .bind(since(days))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(lang_from_iso3, lang_to_iso3, calls, input_tokens, output_tokens, cost_usd)| {
+                    LlmLanguagePairUsage {
+                        lang_from_iso3,
+                        lang_to_iso3,
+                        calls,
+                        input_tokens,
+                        output_tokens,
+                        cost_usd,
+                    }
+                },
+            )
+            .collect())
+    }
+}
+
+fn since(days: u32) -> i64 {
+    unix_now() - i64::from(days) * 24 * 60 * 60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const PRICING: LlmPricing = LlmPricing {
+        input_per_mtok: 2.0,
+        output_per_mtok: 8.0,
+    };
+
+    async fn new_ledger(daily_budget_usd: Option<f64>) -> UsageLedger {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("connect :memory:");
+        UsageLedger::new(pool, PRICING, daily_budget_usd)
+            .await
+            .expect("ledger")
+    }
+
+    fn usage(input_tokens: i64, output_tokens: i64) -> LlmUsage {
+        LlmUsage {
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    #[test]
+    fn cost_is_per_million_tokens() {
+        assert_eq!(PRICING.cost_usd(&usage(1_000_000, 500_000)), 6.0);
+    }
+
+    #[tokio::test]
+    async fn aggregates_by_day_and_language_pair() {
+        let ledger = new_ledger(None).await;
+        ledger.record("gpt", "deu", "eng", &usage(1000, 100)).await;
+        ledger.record("gpt", "deu", "eng", &usage(2000, 200)).await;
+        ledger.record("gpt", "fra", "eng", &usage(500_000, 0)).await;
+
+        let daily = ledger.daily(7).await.unwrap();
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].calls, 3);
+        assert_eq!(daily[0].input_tokens, 503_000);
+        assert_eq!(daily[0].output_tokens, 300);
+
+        let pairs = ledger.by_language_pair(7).await.unwrap();
+        assert_eq!(
+            pairs
+                .iter()
+                .map(|p| (p.lang_from_iso3.as_str(), p.calls))
+                .collect::<Vec<_>>(),
+            vec![("fra", 1), ("deu", 2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn budget_is_exceeded_once_spent() {
+        let ledger = new_ledger(Some(1.0)).await;
+        assert!(!ledger.budget_exceeded().await);
+        ledger.record("gpt", "deu", "eng", &usage(250_000, 0)).await;
+        assert!(!ledger.budget_exceeded().await);
+        ledger.record("gpt", "deu", "eng", &usage(0, 62_500)).await;
+        assert!(ledger.budget_exceeded().await);
+
+        assert!(!new_ledger(None).await.budget_exceeded().await);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::app_state::AppState;
+use crate::kaikki::kaikki_lexical_items;
+use crate::llm::chatgpt_lexical_items::{self, LlmCallStats};
+use crate::model::LexicalItemDetail;
+use crate::panlex::{db_check, panlex_lexical_items};
+use crate::tatoeba::tatoeba_lexical_items;
+use crate::upstream::source_error::SourceError;
+use crate::wortschatz_leipzig::leipzig_lexical_items;
+use async_graphql::Enum;
+use axum::http::StatusCode;
+use std::future::Future;
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    Chatgpt,
+    Panlex,
+    Kaikki,
+    Tatoeba,
+    Leipzig,
+}
+
+impl Source {
+    pub const ALL: [Source; 5] = [
+        Source::Chatgpt,
+        Source::Panlex,
+        Source::Kaikki,
+        Source::Tatoeba,
+        Source::Leipzig,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Chatgpt => "chatgpt",
+            Source::Panlex => "panlex",
+            Source::Kaikki => "kaikki",
+            Source::Tatoeba => "tatoeba",
+            Source::Leipzig => "leipzig",
+        }
+    }
+
+    /// GraphQL error code reported when this source itself fails, see
+    /// [`SourceError::code`].
+    pub fn upstream_code(&self) -> &'static str {
+        match self {
+            Source::Chatgpt => "UPSTREAM_LLM",
+            Source::Panlex => "PANLEX_SQLITE",
+            Source::Kaikki => "UPSTREAM_KAIKKI",
+            Source::Tatoeba => "UPSTREAM_TATOEBA",
+            Source::Leipzig => "UPSTREAM_LEIPZIG",
+        }
+    }
+}
+
+pub struct SourceFailure {
+    pub source: Source,
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl SourceFailure {
+    fn new(source: Source, e: SourceError) -> Self {
+        Self {
+            source,
+            status: e.status(),
+            code: e.code(source.upstream_code()),
+            message: e.message().to_string(),
+        }
+    }
+
+    /// PanLex is read locally and reports its own statuses.
+    fn panlex((status, message): (StatusCode, String)) -> Self {
+        let code = if status == db_check::DISABLED_STATUS {
+            db_check::DISABLED_CODE
+        } else {
+            Source::Panlex.upstream_code()
+        };
+        Self {
+            source: Source::Panlex,
+            status,
+            code,
+            message,
+        }
+    }
+}
+
+pub struct LookupResult {
+    pub items: Vec<LexicalItemDetail>,
+    pub failures: Vec<SourceFailure>,
+    /// Set when the LLM source answered.
+    pub llm_stats: Option<LlmCallStats>,
+}
+
+/// Queries all `sources` concurrently and merges their details in the order
+/// the sources were requested. A failing source does not fail the lookup,
+/// it is reported in `LookupResult::failures` instead.
+pub async fn lookup(
+    state: &AppState,
+    query: &str,
+    lang_from_iso3: &str,
+    lang_to_iso3: &str,
+    sources: &[Source],
+) -> LookupResult {
+    let enabled = |s: Source| sources.contains(&s);
+    let (chatgpt, panlex, kaikki, tatoeba, leipzig) = tokio::join!(
+        run_if(
+            enabled(Source::Chatgpt),
+            chatgpt_lexical_items::request(
+                state.llm(),
+                state.llm_cache(),
+                state.llm_ledger(),
+                query,
+                lang_from_iso3,
+                lang_to_iso3,
+            )
+        ),
+        run_if(enabled(Source::Panlex), async {
+            let panlex = state.panlex()?;
+            panlex_lexical_items::get(
+                &panlex.pool,
+                &panlex.precomputed,
+                query,
+                lang_from_iso3,
+                lang_to_iso3,
+                &[],
+            )
+            .await
+        }),
+        run_if(
+            enabled(Source::Kaikki),
+            kaikki_lexical_items::get(
+                state.http_client(),
+                state.kaikki_upstream(),
+                state.kaikki(),
+                query,
+                lang_from_iso3,
+                None,
+                None,
+            )
+        ),
+        run_if(
+            enabled(Source::Tatoeba),
+            tatoeba_lexical_items::get_details(
+                state.http_client(),
+                state.tatoeba_upstream(),
+                state.tatoeba(),
+                query,
+                lang_from_iso3,
+                lang_to_iso3,
+                None,
+            )
+        ),
+        run_if(
+            enabled(Source::Leipzig),
+            leipzig_lexical_items::get(
+                state.http_client(),
+                state.leipzig_upstream(),
+                state.leipzig_corpora(),
+                query,
+                lang_from_iso3,
+                None,
+                None,
+            )
+        ),
+    );
+
+    let llm_stats = chatgpt
+        .as_ref()
+        .and_then(|r| r.as_ref().ok())
+        .map(|r| r.stats);
+    let failed = |source| move |e| SourceFailure::new(source, e);
+    let mut results = [
+        (
+            Source::Chatgpt,
+            chatgpt.map(|r| r.map(|r| r.items).map_err(failed(Source::Chatgpt))),
+        ),
+        (
+            Source::Panlex,
+            panlex.map(|r| r.map_err(SourceFailure::panlex)),
+        ),
+        (
+            Source::Kaikki,
+            kaikki.map(|r| r.map_err(failed(Source::Kaikki))),
+        ),
+        (
+            Source::Tatoeba,
+            tatoeba.map(|r| r.map_err(failed(Source::Tatoeba))),
+        ),
+        (
+            Source::Leipzig,
+            leipzig.map(|r| r.map_err(failed(Source::Leipzig))),
+        ),
+    ];
+
+    let mut out = LookupResult {
+        items: Vec::new(),
+        failures: Vec::new(),
+        llm_stats,
+    };
+    for source in sources {
+        let Some((_, result)) = results.iter_mut().find(|(s, _)| s == source) else {
+            continue;
+        };
+        // `take` makes duplicated entries in `sources` harmless.
+        match result.take() {
+            Some(Ok(items)) => {
+                for item in items {
+                    if !out.items.contains(&item) {
+                        out.items.push(item);
+                    }
+                }
+            }
+            Some(Err(failure)) => out.failures.push(failure),
+            None => {}
+        }
+    }
+    out
+}
+
+async fn run_if<T>(enabled: bool, fut: impl Future<Output = T>) -> Option<T> {
+    if enabled { Some(fut.await) } else { None }
+}
@@ -1,42 +1,248 @@
 mod app_state;
 mod graphql;
 mod llm;
+mod lookup;
 mod model;
+mod normalize;
 mod panlex;
 mod util;
 mod kaikki;
+mod languages;
 mod tatoeba;
+mod upstream;
 mod wortschatz_leipzig;
 
-use app_state::AppState;
+use app_state::{AppState, LocalDb, PanlexDb};
 
 use async_graphql::http::GraphiQLSource;
 use async_graphql_axum::GraphQL;
 use axum::{response::Html, routing::get, Router};
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use graphql::schema::{build_schema, AppSchema};
+use llm::chatgpt_lexical_items;
+use llm::lexical_cache::LexicalCache;
+use llm::llm_provider::LlmConfig;
+use llm::usage_ledger::{LlmPricing, UsageLedger};
+use panlex::{db_check, prepare};
+use panlex::sqlite::{is_lang_selector, Precomputed};
+use sqlx::sqlite::SqliteConnectOptions;
 use sqlx::SqlitePool;
+use kaikki::kaikki_store::KaikkiStore;
+use tatoeba::tatoeba_store::TatoebaStore;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::{
     DefaultMakeSpan, DefaultOnFailure, DefaultOnRequest, DefaultOnResponse, TraceLayer,
 };
-use tracing::Level;
+use tracing::{info, warn, Level};
 use tracing_subscriber::{fmt, EnvFilter};
 
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Without a subcommand, the server is started.
+    #[command(flatten)]
+    serve: Option<Args>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Loads a Wiktextract JSONL dump (e.g. from kaikki.org) into a local
+    /// SQLite DB, which the server then uses instead of kaikki.org.
+    IngestKaikki(IngestKaikkiArgs),
+    /// Imports the Tatoeba sentence and link exports into a local SQLite DB
+    /// with a full-text index, which the server then uses instead of tatoeba.org.
+    ImportTatoeba(ImportTatoebaArgs),
+    /// Validates a downloaded PanLex DB and adds the indexes which make
+    /// lookups fast, and optionally the expression indexes, normalized
+    /// spellings and precomputed translations.
+    PreparePanlex(PreparePanlexArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct IngestKaikkiArgs {
+    /// Uncompressed JSONL dump
+    #[arg(long = "dump", required = true)]
+    dump: PathBuf,
+    #[arg(long = "kaikki-sqlite-db-path", required = true)]
+    kaikki_sqlite_db_path: String,
+    /// Only ingest entries of this language; all languages by default.
+    #[arg(long = "lang-iso3")]
+    lang_iso3: Option<String>,
+    /// Language of the Wiktionary edition the dump was extracted from.
+    #[arg(long = "edition-iso3", default_value = "eng")]
+    edition_iso3: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct ImportTatoebaArgs {
+    /// Uncompressed `sentences.csv` or `sentences_detailed.csv`
+    #[arg(long = "sentences", required = true)]
+    sentences: PathBuf,
+    /// Uncompressed `links.csv`
+    #[arg(long = "links", required = true)]
+    links: PathBuf,
+    #[arg(long = "tatoeba-sqlite-db-path", required = true)]
+    tatoeba_sqlite_db_path: String,
+    /// Only import sentences of these languages, e.g. `deu,eng`; all by default.
+    #[arg(long = "langs", value_delimiter = ',')]
+    langs: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct PreparePanlexArgs {
+    #[arg(long = "panlex-sqlite-db-path", required = true)]
+    panlex_sqlite_db_path: String,
+    /// Precompute the translations of these language pairs, e.g.
+    /// `deu:eng,eng:deu`; languages as passed to lookups.
+    #[arg(long = "translation-pairs", value_delimiter = ',', value_parser = parse_lang_pair)]
+    translation_pairs: Vec<(String, String)>,
+    /// Build the indexes over the expressions used by `autocomplete` and
+    /// the "did you mean" suggestions of lookups.
+    #[arg(long = "index-expressions", default_value_t = false)]
+    index_expressions: bool,
+    /// Only index these languages or varieties, e.g. `deu,srp-001`; all by default.
+    #[arg(
+        long = "index-langs",
+        value_delimiter = ',',
+        requires = "index_expressions"
+    )]
+    index_langs: Vec<String>,
+    /// Precompute normalized spellings of the expressions, with which
+    /// lookups match e.g. "hund" to "Hund" and "Strasse" to "Straße".
+    #[arg(long = "normalize", default_value_t = false)]
+    normalize: bool,
+    /// Match letter case exactly.
+    #[arg(long = "keep-case", default_value_t = false, requires = "normalize")]
+    keep_case: bool,
+    /// Ignore diacritics, e.g. match "cafe" to "café".
+    #[arg(
+        long = "strip-diacritics",
+        default_value_t = false,
+        requires = "normalize"
+    )]
+    strip_diacritics: bool,
+    /// Lookup timed before and after the preparation.
+    #[arg(long = "benchmark-query", default_value = "water")]
+    benchmark_query: String,
+    #[arg(long = "benchmark-from", default_value = "eng")]
+    benchmark_from: String,
+    #[arg(long = "benchmark-to", default_value = "deu")]
+    benchmark_to: String,
+}
+
+fn parse_lang_pair(pair: &str) -> Result<(String, String), String> {
+    match pair.split_once(':') {
+        Some((from, to)) if is_lang_selector(from) && is_lang_selector(to) => {
+            Ok((from.to_string(), to.to_string()))
+        }
+        _ => Err(format!("expected e.g. deu:eng, got {pair}")),
+    }
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
     #[arg(long = "graphql-parent-path", required = true)]
     graphql_parent_path: String,
-    #[arg(long = "api-key-chatgpt", required = true)]
-    api_key_chat_gpt: String,
+    #[arg(long = "llm-provider", value_enum, default_value_t = LlmProviderKind::Openai)]
+    llm_provider: LlmProviderKind,
+    /// Required by the `openai` provider, optional bearer token for `openai-compatible`.
+    #[arg(long = "api-key-chatgpt")]
+    api_key_chat_gpt: Option<String>,
+    #[arg(long = "llm-model")]
+    llm_model: Option<String>,
+    /// Full endpoint URL, e.g. `http://localhost:11434/v1/chat/completions`.
+    #[arg(long = "llm-url")]
+    llm_url: Option<String>,
+    /// Whether the `openai-compatible` server supports JSON-schema structured outputs.
+    #[arg(long = "llm-structured-outputs", default_value_t = false)]
+    llm_structured_outputs: bool,
     #[arg(long = "panlex-sqlite-db-path", required = true)]
     panlex_sqlite_db_path: String,
+    /// What to do if the PanLex DB is missing tables or empty.
+    #[arg(long = "panlex-unusable", value_enum, default_value_t = PanlexUnusable::Refuse)]
+    panlex_unusable: PanlexUnusable,
+    /// Local DB for LLM bookkeeping, in a writable and persistent directory;
+    /// defaults to `llm.sqlite` next to the PanLex DB.
+    #[arg(long = "llm-sqlite-db-path")]
+    llm_sqlite_db_path: Option<String>,
+    #[arg(long = "llm-cache-ttl-secs", default_value_t = 30 * 24 * 60 * 60)]
+    llm_cache_ttl_secs: u64,
+    /// Prices used to estimate LLM costs, USD per 1M tokens (defaults: gpt-4.1).
+    #[arg(long = "llm-price-input-usd-per-mtok", default_value_t = 2.0)]
+    llm_price_input_usd_per_mtok: f64,
+    #[arg(long = "llm-price-output-usd-per-mtok", default_value_t = 8.0)]
+    llm_price_output_usd_per_mtok: f64,
+    /// Once the estimated LLM spending of the current UTC day reaches this,
+    /// uncached LLM lookups fail with `BUDGET_EXCEEDED`.
+    #[arg(long = "llm-daily-budget-usd")]
+    llm_daily_budget_usd: Option<f64>,
+    /// DB created by the `ingest-kaikki` subcommand; kaikki.org is queried without it.
+    #[arg(long = "kaikki-sqlite-db-path")]
+    kaikki_sqlite_db_path: Option<String>,
+    /// Query kaikki.org for words missing in the local Kaikki DB.
+    #[arg(long = "kaikki-live-fallback", default_value_t = false)]
+    kaikki_live_fallback: bool,
+    /// DB created by the `import-tatoeba` subcommand; tatoeba.org is queried without it.
+    #[arg(long = "tatoeba-sqlite-db-path")]
+    tatoeba_sqlite_db_path: Option<String>,
+    /// Query tatoeba.org when the local Tatoeba DB has no matching sentences.
+    #[arg(long = "tatoeba-live-fallback", default_value_t = false)]
+    tatoeba_live_fallback: bool,
+    /// Enables admin GraphQL operations for callers passing this token.
+    #[arg(long = "admin-token")]
+    admin_token: Option<String>,
     #[arg(long = "port", default_value = "8080")]
     port: String,
     #[arg(long = "cors-permissive", default_value_t = false)]
     cors_permissive: bool,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LlmProviderKind {
+    Openai,
+    OpenaiCompatible,
+    Fake,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum PanlexUnusable {
+    /// Don't start the server.
+    Refuse,
+    /// Start without PanLex; its lookups fail with `PANLEX_DISABLED`.
+    Disable,
+}
+
+fn llm_config(args: &Args) -> Result<LlmConfig, String> {
+    match args.llm_provider {
+        LlmProviderKind::Openai => Ok(LlmConfig::OpenAi {
+            api_key: args
+                .api_key_chat_gpt
+                .clone()
+                .ok_or("--api-key-chatgpt is required for the openai provider")?,
+            model: args.llm_model.clone(),
+            url: args.llm_url.clone(),
+        }),
+        LlmProviderKind::OpenaiCompatible => Ok(LlmConfig::OpenAiCompatible {
+            url: args
+                .llm_url
+                .clone()
+                .ok_or("--llm-url is required for the openai-compatible provider")?,
+            model: args
+                .llm_model
+                .clone()
+                .ok_or("--llm-model is required for the openai-compatible provider")?,
+            api_key: args.api_key_chat_gpt.clone(),
+            structured_outputs: args.llm_structured_outputs,
+        }),
+        LlmProviderKind::Fake => Ok(LlmConfig::Fake),
+    }
+}
+
 async fn graphiql(graphql_parent_path: String) -> Html<String> {
     Html(
         GraphiQLSource::build()
@@ -57,15 +263,253 @@ fn init_tracing() {
         .init();
 }
 
+/// Opens (and creates if missing) a local SQLite DB.
+async fn open_local_db(path: &str) -> Result<SqlitePool, sqlx::Error> {
+    SqlitePool::connect_with(SqliteConnectOptions::from_str(path)?.create_if_missing(true)).await
+}
+
 #[tokio::main]
 async fn main() {
     init_tracing();
-    let args = Args::parse();
-    let panlex_sqlite_pool = SqlitePool::connect(&args.panlex_sqlite_db_path)
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::IngestKaikki(args)) => ingest_kaikki(args).await,
+        Some(Command::ImportTatoeba(args)) => import_tatoeba(args).await,
+        Some(Command::PreparePanlex(args)) => prepare_panlex(args).await,
+        None => {
+            serve(
+                cli.serve
+                    .expect("server args are required without a subcommand"),
+            )
+            .await
+        }
+    }
+}
+
+async fn ingest_kaikki(args: IngestKaikkiArgs) {
+    let pool = open_local_db(&args.kaikki_sqlite_db_path)
+        .await
+        .expect("Can't open the Kaikki DB");
+    let store = KaikkiStore::new(pool)
+        .await
+        .expect("Can't create the Kaikki DB");
+    let stats = store
+        .ingest(
+            open_dump(&args.dump).await,
+            args.lang_iso3.as_deref(),
+            &args.edition_iso3,
+        )
+        .await
+        .expect("Failed to ingest the Kaikki dump");
+    info!(
+        inserted = stats.inserted,
+        filtered = stats.filtered,
+        skipped = stats.skipped,
+        "ingested Kaikki dump"
+    );
+}
+
+async fn open_dump(path: &Path) -> tokio::io::BufReader<tokio::fs::File> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .unwrap_or_else(|e| panic!("Can't open {}: {e}", path.display()));
+    tokio::io::BufReader::new(file)
+}
+
+async fn import_tatoeba(args: ImportTatoebaArgs) {
+    let pool = open_local_db(&args.tatoeba_sqlite_db_path)
+        .await
+        .expect("Can't open the Tatoeba DB");
+    let store = TatoebaStore::new(pool)
+        .await
+        .expect("Can't create the Tatoeba DB");
+    let stats = store
+        .import(
+            open_dump(&args.sentences).await,
+            open_dump(&args.links).await,
+            &args.langs,
+        )
+        .await
+        .expect("Failed to import the Tatoeba exports");
+    info!(
+        sentences = stats.sentences,
+        links = stats.links,
+        skipped = stats.skipped,
+        "imported Tatoeba exports"
+    );
+}
+
+async fn prepare_panlex(args: PreparePanlexArgs) {
+    let pool = SqlitePool::connect(&args.panlex_sqlite_db_path)
         .await
         .expect("Can't connect to the PanLex DB");
-    let app_state = AppState::new(args.api_key_chat_gpt, panlex_sqlite_pool)
-        .expect("Failed to create app state");
+    let problems = prepare::schema_problems(&pool)
+        .await
+        .expect("Can't read the PanLex schema");
+    if !problems.is_empty() {
+        panic!("Unusable PanLex DB: {}", problems.join(", "));
+    }
+
+    let time_lookup = || async {
+        let precomputed = Precomputed::read(&pool)
+            .await
+            .expect("Can't read what was precomputed in the PanLex DB");
+        prepare::time_lookup(
+            &pool,
+            &precomputed,
+            &args.benchmark_query,
+            &args.benchmark_from,
+            &args.benchmark_to,
+        )
+        .await
+    };
+    let before = time_lookup().await.expect("Benchmark lookup failed");
+
+    let indexes = prepare::create_indexes(&pool)
+        .await
+        .expect("Failed to create the PanLex indexes");
+    if args.index_expressions {
+        let stats = panlex::expr_index::build(&pool, &args.index_langs)
+            .await
+            .expect("Failed to index the PanLex expressions");
+        info!(
+            expressions = stats.expressions,
+            "indexed PanLex expressions"
+        );
+    }
+    if args.normalize {
+        let normalization = normalize::Normalization {
+            fold_case: !args.keep_case,
+            strip_diacritics: args.strip_diacritics,
+        };
+        let expressions = panlex::normalized_expr::build(&pool, normalization)
+            .await
+            .expect("Failed to normalize the PanLex expressions");
+        info!(expressions, ?normalization, "normalized PanLex expressions");
+    }
+    for (from, to) in &args.translation_pairs {
+        let translations = prepare::build_translations(&pool, from, to)
+            .await
+            .expect("Failed to precompute PanLex translations");
+        info!(from, to, translations, "precomputed PanLex translations");
+    }
+    prepare::analyze(&pool)
+        .await
+        .expect("Failed to analyze the PanLex DB");
+
+    let after = time_lookup().await.expect("Benchmark lookup failed");
+    info!(
+        ?indexes,
+        before_ms = before.as_secs_f64() * 1000.0,
+        after_ms = after.as_secs_f64() * 1000.0,
+        "prepared PanLex DB"
+    );
+}
+
+/// The PanLex DB if it is usable. Otherwise the server refuses to start,
+/// or runs without PanLex with `--panlex-unusable disable`.
+async fn open_panlex_db(args: &Args) -> Option<PanlexDb> {
+    let checked = match SqlitePool::connect(&args.panlex_sqlite_db_path).await {
+        Ok(pool) => match db_check::check(&pool).await {
+            Ok(summary) => match Precomputed::read(&pool).await {
+                Ok(precomputed) => Ok((PanlexDb { pool, precomputed }, summary)),
+                Err(e) => Err(format!("can't read what was precomputed: {e}")),
+            },
+            Err(problem) => Err(problem),
+        },
+        Err(e) => Err(format!("can't connect: {e}")),
+    };
+    match checked {
+        Ok((panlex, summary)) => {
+            info!(
+                varieties = summary.varieties,
+                sources = summary.sources,
+                normalized = panlex.precomputed.normalization.is_some(),
+                translation_pairs = panlex.precomputed.translation_pairs.len(),
+                "opened PanLex DB"
+            );
+            Some(panlex)
+        }
+        Err(problem) => match args.panlex_unusable {
+            PanlexUnusable::Refuse => panic!("Unusable PanLex DB: {problem}"),
+            PanlexUnusable::Disable => {
+                warn!(%problem, "unusable PanLex DB, PanLex lookups are disabled");
+                None
+            }
+        },
+    }
+}
+
+async fn serve(args: Args) {
+    let panlex = open_panlex_db(&args).await;
+    let llm_sqlite_db_path = args.llm_sqlite_db_path.clone().unwrap_or_else(|| {
+        Path::new(&args.panlex_sqlite_db_path)
+            .with_file_name("llm.sqlite")
+            .to_string_lossy()
+            .into_owned()
+    });
+    let llm_sqlite_pool = open_local_db(&llm_sqlite_db_path)
+        .await
+        .expect("Can't open the LLM DB");
+    let llm_cache = LexicalCache::new(
+        llm_sqlite_pool.clone(),
+        Duration::from_secs(args.llm_cache_ttl_secs),
+    )
+    .await
+    .expect("Can't create the LLM cache");
+    match llm_cache
+        .purge_stale(chatgpt_lexical_items::CACHE_VERSION)
+        .await
+    {
+        Ok(n) => info!(purged = n, "purged stale LLM cache entries"),
+        Err(e) => warn!(error = %e, "failed to purge stale LLM cache entries"),
+    }
+
+    let llm_ledger = UsageLedger::new(
+        llm_sqlite_pool,
+        LlmPricing {
+            input_per_mtok: args.llm_price_input_usd_per_mtok,
+            output_per_mtok: args.llm_price_output_usd_per_mtok,
+        },
+        args.llm_daily_budget_usd,
+    )
+    .await
+    .expect("Can't create the LLM usage ledger");
+
+    let kaikki = match &args.kaikki_sqlite_db_path {
+        Some(path) => Some(LocalDb {
+            store: KaikkiStore::new(open_local_db(path).await.expect("Can't open the Kaikki DB"))
+                .await
+                .expect("Can't create the Kaikki DB"),
+            live_fallback: args.kaikki_live_fallback,
+        }),
+        None => None,
+    };
+    let tatoeba = match &args.tatoeba_sqlite_db_path {
+        Some(path) => Some(LocalDb {
+            store: TatoebaStore::new(
+                open_local_db(path)
+                    .await
+                    .expect("Can't open the Tatoeba DB"),
+            )
+            .await
+            .expect("Can't create the Tatoeba DB"),
+            live_fallback: args.tatoeba_live_fallback,
+        }),
+        None => None,
+    };
+
+    let llm_config = llm_config(&args).unwrap_or_else(|e| panic!("Invalid LLM config: {e}"));
+    let app_state = AppState::new(
+        llm_config,
+        Some(llm_cache),
+        Some(llm_ledger),
+        panlex,
+        kaikki,
+        tatoeba,
+        args.admin_token.clone(),
+    )
+    .expect("Failed to create app state");
     let schema: AppSchema = build_schema(app_state.clone());
 
     let graphql_parent_path = args.graphql_parent_path.clone();
@@ -78,6 +522,10 @@ async fn main() {
             "/ws/sentences/{corpus}/sentences/{term}",
             get(wortschatz_leipzig::wortschatz_leipzig_proxy::wortschatz_leipzig_proxy),
         )
+        .route(
+            "/status/upstreams",
+            get(upstream::upstream_status::upstream_status),
+        )
         .with_state(app_state)
         .layer(
             TraceLayer::new_for_http()
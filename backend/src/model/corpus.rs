@@ -0,0 +1,14 @@
+use async_graphql::SimpleObject;
+
+/// A text corpus of Wortschatz Leipzig.
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct Corpus {
+    /// E.g. "deu_news_2012_1M"
+    pub name: String,
+    pub lang_iso3: String,
+    /// E.g. "news", "wikipedia", "web"
+    pub genre: Option<String>,
+    pub year: Option<i32>,
+    pub size_sentences: Option<i64>,
+}
@@ -1,20 +1,148 @@
 use super::TranslationsSet;
-use async_graphql::{SimpleObject, Union};
+use async_graphql::{Enum, SimpleObject, Union};
 
 #[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
 #[graphql(rename_fields = "camelCase")]
 pub struct Forms {
+    /// Human-readable summary, e.g. "der Hund, -e"
     pub text: String,
+    pub part_of_speech: Option<PartOfSpeech>,
+    pub gender: Option<GrammaticalGender>,
+    /// Definite article, for languages which have them
+    pub article: Option<String>,
+    pub plural: Option<String>,
+    /// E.g. ["gehen", "geht", "ging", "ist gegangen"]; empty for non-verbs
+    pub verb_principal_parts: Vec<String>,
     pub source: String,
 }
 
+impl Forms {
+    /// Forms known only as a human-readable summary.
+    pub fn new(text: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            part_of_speech: None,
+            gender: None,
+            article: None,
+            plural: None,
+            verb_principal_parts: Vec::new(),
+            source: source.into(),
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PartOfSpeech {
+    Noun,
+    ProperNoun,
+    Verb,
+    Adjective,
+    Adverb,
+    Pronoun,
+    Preposition,
+    Conjunction,
+    Interjection,
+    Numeral,
+    Article,
+    Particle,
+    Phrase,
+    Other,
+}
+
+impl PartOfSpeech {
+    /// Accepts both full names ("adjective") and Wiktionary/Kaikki
+    /// abbreviations ("adj"). Unknown values map to `Other`.
+    pub fn parse(s: &str) -> Self {
+        match s.trim().to_lowercase().replace([' ', '-'], "_").as_str() {
+            "noun" => Self::Noun,
+            "name" | "proper_noun" => Self::ProperNoun,
+            "verb" => Self::Verb,
+            "adj" | "adjective" => Self::Adjective,
+            "adv" | "adverb" => Self::Adverb,
+            "pron" | "pronoun" => Self::Pronoun,
+            "prep" | "preposition" | "postp" | "postposition" => Self::Preposition,
+            "conj" | "conjunction" => Self::Conjunction,
+            "intj" | "interjection" => Self::Interjection,
+            "num" | "numeral" => Self::Numeral,
+            "article" | "det" | "determiner" => Self::Article,
+            "particle" => Self::Particle,
+            "phrase" | "proverb" | "idiom" => Self::Phrase,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GrammaticalGender {
+    Masculine,
+    Feminine,
+    Neuter,
+    Common,
+}
+
+impl GrammaticalGender {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "masculine" | "m" | "m." | "м." => Some(Self::Masculine),
+            "feminine" | "f" | "f." | "ж." => Some(Self::Feminine),
+            "neuter" | "n" | "n." | "ср." => Some(Self::Neuter),
+            "common" | "c" | "c." => Some(Self::Common),
+            _ => None,
+        }
+    }
+}
+
 #[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
 #[graphql(rename_fields = "camelCase")]
 pub struct WordTranslations {
     pub translations_set: TranslationsSet,
+    /// Works attesting each translation, in the same order; PanLex only
+    pub translations_sources: Option<Vec<Vec<PanlexSource>>>,
+    pub source: String,
+}
+
+/// A dictionary or other work PanLex took its data from, for attribution.
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct PanlexSource {
+    /// PanLex label, e.g. "eng-deu:Langenscheidt"
+    pub label: String,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub url: Option<String>,
+    /// E.g. "cc-by", "gpl", "copyright"
+    pub license: Option<String>,
+    /// PanLex's rating of the source, 0-9
+    pub quality: i32,
+}
+
+/// Translations not sharing a meaning with the original but reached through
+/// expressions in pivot languages: original → pivot → translation.
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct IndirectTranslations {
+    pub translations_set: TranslationsSet,
+    /// Per translation, in the same order
+    pub support: Vec<PivotSupport>,
+    /// Works linking the query to the pivots and those to each translation,
+    /// in the same order
+    pub translations_sources: Vec<Vec<PanlexSource>>,
     pub source: String,
 }
 
+/// What an indirect translation rests on; more is more reliable.
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct PivotSupport {
+    /// Distinct pivot expressions leading to the translation
+    pub paths: i32,
+    /// Distinct sources of the links from the query to the pivot expressions
+    /// and from those to the translation
+    pub sources: i32,
+    /// Pivot languages of the paths
+    pub via_langs_iso3: Vec<String>,
+}
+
 #[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
 #[graphql(rename_fields = "camelCase")]
 pub struct Synonyms {
@@ -36,11 +164,80 @@ pub struct Example {
     pub source: String,
 }
 
-#[derive(Union, Clone, Debug, PartialEq, Eq)]
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct Pronunciation {
+    /// E.g. "/hʊnt/"
+    pub ipa: Option<String>,
+    pub audio_url: Option<String>,
+    /// Accent or region, e.g. ["Received-Pronunciation"]
+    pub tags: Vec<String>,
+    pub source: String,
+}
+
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct Etymology {
+    pub text: String,
+    pub source: String,
+}
+
+/// How common a word is in a corpus.
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct Frequency {
+    /// Leipzig frequency class: the most frequent word is about 2^class
+    /// times as frequent as this one, so 0 is the most frequent
+    pub frequency_class: i32,
+    /// 1 is the most frequent word
+    pub rank: i64,
+    /// Occurrences in the corpus
+    pub count: Option<i64>,
+    pub corpus: String,
+    pub source: String,
+}
+
+#[derive(SimpleObject, Clone, Debug, PartialEq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct Collocate {
+    pub word: String,
+    /// Co-occurrences with the looked up word
+    pub count: i64,
+    /// Log-likelihood significance of the co-occurrence
+    pub significance: f64,
+}
+
+/// Words most significantly occurring right before (`left`) and right
+/// after (`right`) a word.
+#[derive(SimpleObject, Clone, Debug, PartialEq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct Collocations {
+    pub left: Vec<Collocate>,
+    pub right: Vec<Collocate>,
+    pub corpus: String,
+    pub source: String,
+}
+
+/// Similar spellings of a word which was not found.
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct DidYouMean {
+    /// The closest first
+    pub suggestions: Vec<String>,
+    pub source: String,
+}
+
+#[derive(Union, Clone, Debug, PartialEq)]
 pub enum LexicalItemDetail {
     Forms(Forms),
     WordTranslations(WordTranslations),
+    IndirectTranslations(IndirectTranslations),
     Synonyms(Synonyms),
     Explanation(Explanation),
     Example(Example),
+    Pronunciation(Pronunciation),
+    Etymology(Etymology),
+    Frequency(Frequency),
+    Collocations(Collocations),
+    DidYouMean(DidYouMean),
 }
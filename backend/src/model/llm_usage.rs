@@ -0,0 +1,32 @@
+use async_graphql::SimpleObject;
+
+#[derive(SimpleObject, Clone, Debug, PartialEq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct LlmDailyUsage {
+    /// UTC day, `YYYY-MM-DD`
+    pub day: String,
+    pub calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+}
+
+#[derive(SimpleObject, Clone, Debug, PartialEq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct LlmLanguagePairUsage {
+    pub lang_from_iso3: String,
+    pub lang_to_iso3: String,
+    pub calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+}
+
+#[derive(SimpleObject, Clone, Debug, PartialEq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct LlmUsageReport {
+    pub daily: Vec<LlmDailyUsage>,
+    pub by_language_pair: Vec<LlmLanguagePairUsage>,
+    pub spent_today_usd: f64,
+    pub daily_budget_usd: Option<f64>,
+}
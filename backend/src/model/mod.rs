@@ -1,8 +1,14 @@
+mod corpus;
 pub(crate) mod lexical_item_detail;
+mod llm_usage;
+mod panlex_variety;
 mod sentence;
 mod translations_set;
 
+pub use corpus::Corpus;
 pub use lexical_item_detail::LexicalItemDetail;
 pub use lexical_item_detail::WordTranslations;
+pub use llm_usage::{LlmDailyUsage, LlmLanguagePairUsage, LlmUsageReport};
+pub use panlex_variety::PanlexVariety;
 pub use sentence::Sentence;
 pub use translations_set::TranslationsSet;
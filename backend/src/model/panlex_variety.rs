@@ -0,0 +1,15 @@
+use async_graphql::SimpleObject;
+
+/// A PanLex language variety, e.g. Serbian in Latin script.
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(rename_fields = "camelCase")]
+pub struct PanlexVariety {
+    /// E.g. "srp-001"; accepted wherever a PanLex language is expected
+    pub uid: String,
+    pub lang_iso3: String,
+    pub var_code: i32,
+    /// Name of the variety in the variety itself
+    pub name: Option<String>,
+    pub region: Option<String>,
+    pub script: Option<String>,
+}
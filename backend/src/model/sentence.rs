@@ -6,6 +6,11 @@ pub struct Sentence {
     pub text: String,
     pub lang_iso3: String,
     pub source: String,
+    /// ID of the sentence at its source, e.g. the Tatoeba sentence number
+    pub source_id: Option<String>,
+    /// Author or owner of the sentence at its source
+    pub owner: Option<String>,
+    pub license: Option<String>,
 }
 
 impl Sentence {
@@ -18,6 +23,9 @@ impl Sentence {
             text: text.into(),
             lang_iso3: lang_iso3.into(),
             source: source.into(),
+            source_id: None,
+            owner: None,
+            license: None,
         }
     }
 }
@@ -0,0 +1,92 @@
+//! Normalization of words, so that spellings which users consider the same
+//! match: "Hund" and "hund", "Straße" and "Strasse", or a "ü" typed as "u"
+//! followed by a combining diaeresis and the precomposed one.
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// How much spellings may differ and still match. Every normalization
+/// includes Unicode NFC and the equivalences of the word's language.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Normalization {
+    /// "Hund" matches "hund"
+    pub fold_case: bool,
+    /// "café" matches "cafe"
+    pub strip_diacritics: bool,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            fold_case: true,
+            strip_diacritics: false,
+        }
+    }
+}
+
+impl Normalization {
+    /// Key of `text` in `lang_iso3`: texts with equal keys match.
+    pub fn key(&self, text: &str, lang_iso3: &str) -> String {
+        let mut key = nfc(text);
+        if self.fold_case {
+            key = key.to_lowercase();
+        }
+        for (letter, replacement) in equivalences(lang_iso3) {
+            if key.contains(*letter) {
+                key = key.replace(*letter, replacement);
+            }
+        }
+        if self.strip_diacritics {
+            key = key.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect();
+        }
+        key
+    }
+}
+
+/// `text` trimmed and in Unicode NFC, the form stored by all our sources.
+pub fn nfc(text: &str) -> String {
+    text.trim().nfc().collect()
+}
+
+/// Letters commonly written in place of others in `lang_iso3`.
+fn equivalences(lang_iso3: &str) -> &'static [(char, &'static str)] {
+    match lang_iso3 {
+        "deu" | "gsw" => &[('ß', "ss"), ('ẞ', "SS")],
+        "rus" | "bel" => &[('ё', "е"), ('Ё', "Е")],
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_of_equivalent_spellings_are_equal() {
+        let default = Normalization::default();
+        assert_eq!(default.key(" Hund ", "deu"), "hund");
+        assert_eq!(default.key("Gru\u{308}n", "deu"), "grün");
+        assert_eq!(default.key("STRAẞE", "deu"), "strasse");
+        assert_eq!(default.key("Straße", "deu"), default.key("Strasse", "deu"));
+        assert_eq!(default.key("Ёлка", "rus"), "елка");
+        // Only the equivalences of the word's language apply.
+        assert_eq!(default.key("Ёлка", "eng"), "ёлка");
+    }
+
+    #[test]
+    fn case_and_diacritics_are_configurable() {
+        let exact = Normalization {
+            fold_case: false,
+            strip_diacritics: false,
+        };
+        assert_eq!(exact.key("Ёлка", "rus"), "Елка");
+        assert_eq!(exact.key("Cafe\u{301}", "fra"), "Café");
+
+        let loose = Normalization {
+            fold_case: true,
+            strip_diacritics: true,
+        };
+        assert_eq!(loose.key("Café", "fra"), "cafe");
+        assert_eq!(loose.key("Grün", "deu"), "grun");
+    }
+}
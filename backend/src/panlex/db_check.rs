@@ -0,0 +1,81 @@
+//! Check of the PanLex DB when the server starts, so that a wrong or
+//! truncated file is noticed before the first lookup.
+
+use super::prepare::schema_problems;
+use axum::http::StatusCode;
+use sqlx::SqlitePool;
+
+/// Status of PanLex lookups when the server runs without PanLex because
+/// its DB was unusable.
+pub const DISABLED_STATUS: StatusCode = StatusCode::SERVICE_UNAVAILABLE;
+/// Error code of PanLex lookups when the server runs without PanLex.
+pub const DISABLED_CODE: &str = "PANLEX_DISABLED";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PanlexSummary {
+    pub varieties: i64,
+    pub sources: i64,
+}
+
+/// What the DB contains; `Err` with the reason if lookups cannot work.
+pub async fn check(pool: &SqlitePool) -> Result<PanlexSummary, String> {
+    let problems = schema_problems(pool).await.map_err(|e| e.to_string())?;
+    if !problems.is_empty() {
+        return Err(problems.join(", "));
+    }
+    let count = |table: &'static str| async move {
+        sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM {table}"))
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("can't count {table}: {e}"))
+    };
+    let summary = PanlexSummary {
+        varieties: count("langvar").await?,
+        sources: count("source").await?,
+    };
+    if summary.varieties == 0 {
+        return Err("no language varieties".to_string());
+    }
+    // Counting the expressions would scan tens of millions of rows.
+    let has_expressions: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM expr)")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("can't read expr: {e}"))?;
+    if !has_expressions {
+        return Err("no expressions".to_string());
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panlex::sqlite::tests::new_test_pool;
+
+    #[tokio::test]
+    async fn empty_or_foreign_dbs_are_unusable() {
+        let foreign = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let err = check(&foreign).await.expect_err("Err");
+        assert!(err.starts_with("missing table langvar"), "{err}");
+
+        let pool = new_test_pool().await;
+        assert_eq!(check(&pool).await, Err("no language varieties".to_string()));
+
+        sqlx::query(
+            r#"
+            INSERT INTO langvar(id, lang_code, var_code, uid) VALUES (100,'deu',0,'deu-000');
+            INSERT INTO expr(id, langvar, txt) VALUES (1000,100,'Imker'), (1001,100,'Hund');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            check(&pool).await,
+            Ok(PanlexSummary {
+                varieties: 1,
+                sources: 0,
+            })
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Prefix and trigram indexes over the PanLex expressions, for
+//! autocompletion and "did you mean" suggestions. The prefix index is a
+//! B-tree over `expr (langvar, txt)`; the trigram index is an FTS5 table
+//! over the expressions of the language varieties it was built for, see
+//! [`build`]. Its `lang` column holds the ISO 639-3 code and UID of the
+//! variety, so that suggestions are searched in one language only.
+
+use super::sqlite::{Precomputed, iso3_of, variety_ids};
+use axum::http::StatusCode;
+use sqlx::SqlitePool;
+use tracing::{error, info};
+
+/// Expressions sharing trigrams with a misspelled query which are ranked
+/// by their edit distance to it.
+const MAX_CANDIDATES: i64 = 200;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct IndexStats {
+    pub expressions: u64,
+}
+
+/// (Re)builds the indexes for the varieties selected by `langs` (ISO 639-3
+/// codes or variety UIDs), all of them if it is empty. The trigram index
+/// grows with the number of expressions, so it is best limited to the
+/// languages users look words up in.
+pub async fn build(pool: &SqlitePool, langs: &[String]) -> Result<IndexStats, sqlx::Error> {
+    info!("creating PanLex prefix index");
+    sqlx::query("CREATE INDEX IF NOT EXISTS expr_langvar_txt ON expr (langvar, txt)")
+        .execute(pool)
+        .await?;
+
+    let mut tx = pool.begin().await?;
+    for ddl in [
+        "DROP TABLE IF EXISTS expr_trigram",
+        "DROP VIEW IF EXISTS expr_trigram_content",
+        r#"
+        CREATE VIEW expr_trigram_content AS
+        SELECT ex.ex AS id, ex.tt AS txt, lv.lc || ' ' || lv.uid AS lang
+        FROM ex
+        JOIN lv ON lv.lv = ex.lv
+        "#,
+        // Only the selected rows are inserted, the texts are read from the view.
+        r#"
+        CREATE VIRTUAL TABLE expr_trigram USING fts5 (
+            txt, lang,
+            content = 'expr_trigram_content', content_rowid = 'id', tokenize = 'trigram'
+        )
+        "#,
+    ] {
+        sqlx::query(ddl).execute(&mut *tx).await?;
+    }
+    info!(?langs, "building PanLex trigram index");
+    let mut sql = r#"
+        INSERT INTO expr_trigram (rowid, txt, lang)
+        SELECT ex.ex, ex.tt, lv.lc || ' ' || lv.uid
+        FROM ex
+        JOIN lv ON lv.lv = ex.lv
+    "#
+    .to_string();
+    if !langs.is_empty() {
+        let placeholders = (1..=langs.len())
+            .map(|i| format!("?{i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        sql += &format!(" WHERE lv.uid IN ({placeholders}) OR lv.lc IN ({placeholders})");
+    }
+    let mut q = sqlx::query(&sql);
+    for lang in langs {
+        q = q.bind(lang);
+    }
+    let expressions = q.execute(&mut *tx).await?.rows_affected();
+    tx.commit().await?;
+    Ok(IndexStats { expressions })
+}
+
+/// Up to `limit` expressions of `lang` starting with `prefix`: the ones
+/// starting with it exactly first, then the ones whose normalized spelling
+/// starts with the normalized prefix, if `prepare-panlex --normalize` was
+/// run, then, if the trigram index was built, the ones starting with it in
+/// other letter cases.
+pub async fn autocomplete(
+    db_pool: &SqlitePool,
+    precomputed: &Precomputed,
+    prefix: &str,
+    lang: &str,
+    limit: usize,
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let prefix = prefix.trim();
+    let Some(ids) = variety_ids(db_pool, &[lang]).await? else {
+        return Ok(Vec::new());
+    };
+    let mut out: Vec<String> = sqlx::query_scalar(&format!(
+        r#"
+        SELECT DISTINCT ex.tt
+        FROM ex
+        WHERE ex.lv IN ({ids})
+          AND ex.tt >= ?1 AND ex.tt < ?2
+        ORDER BY ex.tt
+        LIMIT ?3
+        "#
+    ))
+    .bind(prefix)
+    .bind(upper_bound(prefix))
+    .bind(limit as i64)
+    .fetch_all(db_pool)
+    .await
+    .map_err(query_error)?;
+
+    if out.len() < limit
+        && let Some(normalization) = precomputed.normalization
+    {
+        let key = normalization.key(prefix, iso3_of(lang));
+        let normalized: Vec<String> = sqlx::query_scalar(&format!(
+            r#"
+            SELECT ex.tt
+            FROM expr
+            JOIN ex ON ex.ex = expr.id
+            WHERE expr.langvar IN ({ids})
+              AND expr.txt_norm >= ?1 AND expr.txt_norm < ?2
+            GROUP BY ex.tt
+            ORDER BY MIN(expr.txt_norm), ex.tt
+            LIMIT ?3
+            "#
+        ))
+        .bind(&key)
+        .bind(upper_bound(&key))
+        .bind((limit + out.len()) as i64)
+        .fetch_all(db_pool)
+        .await
+        .map_err(query_error)?;
+        push_new(&mut out, normalized, limit);
+    }
+
+    // The trigram index only helps with 3 characters or more, and
+    // wildcards cannot be escaped in its LIKE patterns.
+    if out.len() < limit
+        && 3 <= prefix.chars().count()
+        && !prefix.contains(['%', '_'])
+        && has_trigram_index(db_pool).await?
+    {
+        let others: Vec<String> = sqlx::query_scalar(&format!(
+            r#"
+            SELECT DISTINCT ex.tt
+            FROM expr_trigram
+            JOIN ex ON ex.ex = expr_trigram.rowid
+            WHERE expr_trigram.txt LIKE ?1
+              AND ex.lv IN ({ids})
+            ORDER BY ex.tt
+            LIMIT ?2
+            "#
+        ))
+        .bind(format!("{prefix}%"))
+        .bind((limit + out.len()) as i64)
+        .fetch_all(db_pool)
+        .await
+        .map_err(query_error)?;
+        push_new(&mut out, others, limit);
+    }
+    Ok(out)
+}
+
+/// Every text starting with `prefix` sorts before this one.
+fn upper_bound(prefix: &str) -> String {
+    format!("{prefix}{}", char::MAX)
+}
+
+/// Appends the `texts` which are not in `out` yet, up to `limit` in total.
+fn push_new(out: &mut Vec<String>, texts: Vec<String>, limit: usize) {
+    for text in texts {
+        if limit <= out.len() {
+            break;
+        }
+        if !out.contains(&text) {
+            out.push(text);
+        }
+    }
+}
+
+/// Up to `limit` expressions of `lang` similar to `query`, the closest
+/// first; none without the trigram index or for queries shorter than a
+/// trigram. Spellings differing only in letter case come first.
+pub async fn suggest(
+    db_pool: &SqlitePool,
+    query: &str,
+    lang: &str,
+    limit: usize,
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let query = query.trim();
+    let Some(expression) = trigram_match_expression(query, lang) else {
+        return Ok(Vec::new());
+    };
+    if !has_trigram_index(db_pool).await? {
+        return Ok(Vec::new());
+    }
+
+    let candidates: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT ex.tt
+        FROM expr_trigram
+        JOIN ex ON ex.ex = expr_trigram.rowid
+        WHERE expr_trigram MATCH ?1
+        ORDER BY rank
+        LIMIT ?2
+        "#,
+    )
+    .bind(expression)
+    .bind(MAX_CANDIDATES)
+    .fetch_all(db_pool)
+    .await
+    .map_err(query_error)?;
+
+    let folded = query.to_lowercase();
+    // About one typo per four letters.
+    let max_distance = 1 + folded.chars().count() / 4;
+    let mut ranked: Vec<(usize, String)> = candidates
+        .into_iter()
+        .filter(|c| c != query)
+        .map(|c| (edit_distance(&folded, &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    ranked.sort();
+    ranked.dedup_by(|a, b| a.1 == b.1);
+    Ok(ranked.into_iter().take(limit).map(|(_, c)| c).collect())
+}
+
+async fn has_trigram_index(db_pool: &SqlitePool) -> Result<bool, (StatusCode, String)> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'expr_trigram')",
+    )
+    .fetch_one(db_pool)
+    .await
+    .map_err(query_error)
+}
+
+/// FTS5 query matching texts of `lang` sharing at least one trigram with
+/// `query`. Everything is quoted, so user input cannot inject FTS syntax.
+fn trigram_match_expression(query: &str, lang: &str) -> Option<String> {
+    let quote = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+    let chars: Vec<char> = query.to_lowercase().chars().collect();
+    let mut trigrams: Vec<String> = chars
+        .windows(3)
+        .map(|w| quote(&w.iter().collect::<String>()))
+        .collect();
+    trigrams.sort();
+    trigrams.dedup();
+    (!trigrams.is_empty()).then(|| {
+        format!(
+            "lang : {} AND txt : ({})",
+            quote(lang),
+            trigrams.join(" OR ")
+        )
+    })
+}
+
+/// Levenshtein distance in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn query_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!(error = %e, "failed to execute PanLex expression index query");
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::Normalization;
+    use crate::panlex::normalized_expr;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn expressions_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        for sql in [
+            "CREATE TABLE langvar (id integer PRIMARY KEY, lang_code text, uid text)",
+            "CREATE TABLE expr (id integer PRIMARY KEY, langvar integer, txt text)",
+            "CREATE VIEW lv AS SELECT id as lv, lang_code as lc, uid FROM langvar",
+            "CREATE VIEW ex AS SELECT id as ex, langvar as lv, txt as tt FROM expr",
+            "INSERT INTO langvar VALUES (100, 'deu', 'deu-000'), (300, 'eng', 'eng-000')",
+            r#"INSERT INTO expr VALUES
+                 (1, 100, 'Hund'), (2, 100, 'Hundehütte'), (3, 100, 'hundert'),
+                 (4, 100, 'Imker'), (5, 100, 'Imkerei'), (6, 300, 'Hungary')"#,
+        ] {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+        pool
+    }
+
+    #[tokio::test]
+    async fn autocomplete_prefers_exact_prefixes() {
+        let pool = expressions_pool().await;
+        let exact = autocomplete(&pool, &Precomputed::default(), "Hun", "deu", 10)
+            .await
+            .expect("Ok");
+        assert_eq!(exact, vec!["Hund", "Hundehütte"]);
+
+        let stats = build(&pool, &["deu".to_string()]).await.expect("Ok");
+        assert_eq!(stats, IndexStats { expressions: 5 });
+        let all = autocomplete(&pool, &Precomputed::default(), "Hun", "deu", 10)
+            .await
+            .expect("Ok");
+        assert_eq!(all, vec!["Hund", "Hundehütte", "hundert"]);
+        let limited = autocomplete(&pool, &Precomputed::default(), "Hun", "deu", 1)
+            .await
+            .expect("Ok");
+        assert_eq!(limited, vec!["Hund"]);
+    }
+
+    #[tokio::test]
+    async fn autocomplete_matches_normalized_prefixes() {
+        let pool = expressions_pool().await;
+        normalized_expr::build(&pool, Normalization::default())
+            .await
+            .expect("Ok");
+        let precomputed = Precomputed::read(&pool).await.expect("Ok");
+        let all = autocomplete(&pool, &precomputed, "hun", "deu", 10)
+            .await
+            .expect("Ok");
+        assert_eq!(all, vec!["hundert", "Hund", "Hundehütte"]);
+    }
+
+    #[tokio::test]
+    async fn suggestions_need_the_trigram_index() {
+        let pool = expressions_pool().await;
+        assert!(
+            suggest(&pool, "Imkar", "deu", 5)
+                .await
+                .expect("Ok")
+                .is_empty()
+        );
+
+        build(&pool, &[]).await.expect("Ok");
+        assert_eq!(
+            suggest(&pool, "Imkar", "deu", 5).await.expect("Ok"),
+            vec!["Imker"]
+        );
+        assert_eq!(
+            suggest(&pool, "hund", "deu", 5).await.expect("Ok"),
+            vec!["Hund"]
+        );
+        assert!(suggest(&pool, "Hu", "deu", 5).await.expect("Ok").is_empty());
+    }
+
+    #[tokio::test]
+    async fn suggestions_are_searched_in_one_language() {
+        let pool = expressions_pool().await;
+        build(&pool, &[]).await.expect("Ok");
+        assert_eq!(
+            suggest(&pool, "Hungari", "eng", 5).await.expect("Ok"),
+            vec!["Hungary"]
+        );
+        assert!(
+            suggest(&pool, "Hungari", "deu", 5)
+                .await
+                .expect("Ok")
+                .is_empty()
+        );
+        assert_eq!(
+            suggest(&pool, "Imkar", "deu-000", 5).await.expect("Ok"),
+            vec!["Imker"]
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_characters() {
+        assert_eq!(edit_distance("imkar", "imker"), 1);
+        assert_eq!(edit_distance("hütte", "hutte"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
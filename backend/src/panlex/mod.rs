@@ -1,2 +1,6 @@
+pub(crate) mod db_check;
+pub(crate) mod expr_index;
+pub(crate) mod normalized_expr;
 pub(crate) mod panlex_lexical_items;
-mod sqlite;
+pub(crate) mod prepare;
+pub(crate) mod sqlite;
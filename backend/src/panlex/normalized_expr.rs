@@ -0,0 +1,104 @@
+//! Precomputed normalization keys of the PanLex expressions, in the
+//! `expr.txt_norm` column. Once built, lookups match the key of the query
+//! against it instead of matching the query exactly.
+
+use crate::normalize::Normalization;
+use sqlx::SqlitePool;
+use tracing::info;
+
+/// Expressions are normalized in transactions of this size.
+const BATCH: i64 = 10_000;
+
+/// (Re)computes `expr.txt_norm` with `normalization`, and returns the
+/// number of expressions.
+pub async fn build(pool: &SqlitePool, normalization: Normalization) -> Result<u64, sqlx::Error> {
+    // Until the column is complete, lookups keep matching exactly.
+    sqlx::query("DROP TABLE IF EXISTS expr_normalization")
+        .execute(pool)
+        .await?;
+    let has_column: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info('expr') WHERE name = 'txt_norm')",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !has_column {
+        sqlx::query("ALTER TABLE expr ADD COLUMN txt_norm TEXT")
+            .execute(pool)
+            .await?;
+    }
+
+    let mut expressions = 0;
+    let mut last_id = i64::MIN;
+    loop {
+        let batch: Vec<(i64, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT ex.ex, ex.tt, lv.lc
+            FROM ex
+            LEFT JOIN lv ON lv.lv = ex.lv
+            WHERE ex.ex > ?1
+            ORDER BY ex.ex
+            LIMIT ?2
+            "#,
+        )
+        .bind(last_id)
+        .bind(BATCH)
+        .fetch_all(pool)
+        .await?;
+        let Some((id, _, _)) = batch.last() else {
+            break;
+        };
+        last_id = *id;
+
+        let mut tx = pool.begin().await?;
+        for (id, txt, lang_iso3) in &batch {
+            let key = normalization.key(txt, lang_iso3.as_deref().unwrap_or_default());
+            sqlx::query("UPDATE expr SET txt_norm = ?1 WHERE id = ?2")
+                .bind(key)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        expressions += batch.len() as u64;
+        info!(expressions, "normalizing PanLex expressions");
+    }
+
+    info!("indexing normalized PanLex expressions");
+    for sql in [
+        "CREATE INDEX IF NOT EXISTS expr_txt_norm_langvar ON expr (txt_norm, langvar)",
+        r#"
+        CREATE TABLE expr_normalization (
+            fold_case        INTEGER NOT NULL,
+            strip_diacritics INTEGER NOT NULL
+        )
+        "#,
+    ] {
+        sqlx::query(sql).execute(pool).await?;
+    }
+    sqlx::query("INSERT INTO expr_normalization (fold_case, strip_diacritics) VALUES (?1, ?2)")
+        .bind(normalization.fold_case)
+        .bind(normalization.strip_diacritics)
+        .execute(pool)
+        .await?;
+    Ok(expressions)
+}
+
+/// How `expr.txt_norm` was computed; `None` if it was not (completely).
+pub async fn normalization(pool: &SqlitePool) -> Result<Option<Normalization>, sqlx::Error> {
+    let built: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'expr_normalization')",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !built {
+        return Ok(None);
+    }
+    let row: Option<(bool, bool)> =
+        sqlx::query_as("SELECT fold_case, strip_diacritics FROM expr_normalization")
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|(fold_case, strip_diacritics)| Normalization {
+        fold_case,
+        strip_diacritics,
+    }))
+}
@@ -1,20 +1,56 @@
 use crate::model::LexicalItemDetail;
-use crate::panlex::sqlite::{get_synonyms, get_translations};
+use crate::model::lexical_item_detail::DidYouMean;
+use crate::panlex::expr_index;
+use crate::panlex::sqlite::{
+    Precomputed, get_indirect_translations, get_synonyms, get_translations,
+};
 use axum::http::StatusCode;
 use sqlx::SqlitePool;
 
+/// Suggestions when `query` has neither translations nor synonyms.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Translations and synonyms of `query`. Translations through
+/// `pivot_langs_iso3` are added as indirect ones; none if it is empty.
+/// If nothing is found, similar spellings are suggested instead.
 pub async fn get(
     db_pool: &SqlitePool,
+    precomputed: &Precomputed,
     query: &str,
     lang_from_iso3: &str,
     lang_to_iso3: &str,
+    pivot_langs_iso3: &[String],
 ) -> Result<Vec<LexicalItemDetail>, (StatusCode, String)> {
     let mut out = Vec::<LexicalItemDetail>::new();
-    if let Some(wt) = get_translations(db_pool, query, lang_from_iso3, lang_to_iso3).await? {
+    if let Some(wt) =
+        get_translations(db_pool, precomputed, query, lang_from_iso3, lang_to_iso3).await?
+    {
         out.push(LexicalItemDetail::WordTranslations(wt));
     }
-    if let Some(syn) = get_synonyms(db_pool, query, lang_from_iso3).await? {
+    if let Some(it) = get_indirect_translations(
+        db_pool,
+        precomputed,
+        query,
+        lang_from_iso3,
+        lang_to_iso3,
+        pivot_langs_iso3,
+    )
+    .await?
+    {
+        out.push(LexicalItemDetail::IndirectTranslations(it));
+    }
+    if let Some(syn) = get_synonyms(db_pool, precomputed, query, lang_from_iso3).await? {
         out.push(LexicalItemDetail::Synonyms(syn));
     }
+    if out.is_empty() {
+        let suggestions =
+            expr_index::suggest(db_pool, query, lang_from_iso3, MAX_SUGGESTIONS).await?;
+        if !suggestions.is_empty() {
+            out.push(LexicalItemDetail::DidYouMean(DidYouMean {
+                suggestions,
+                source: "panlex".to_string(),
+            }));
+        }
+    }
     Ok(out)
 }
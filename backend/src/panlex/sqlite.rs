@@ -1,43 +1,179 @@
-use crate::model::lexical_item_detail::Synonyms;
-use crate::model::{Sentence, TranslationsSet, WordTranslations};
+//! Languages are selected by an ISO 639-3 code, matching all varieties of
+//! the language, or by the UID of one variety, e.g. "srp-001" for Serbian in
+//! Latin script (see [`get_varieties`]).
+
+use crate::model::lexical_item_detail::{
+    IndirectTranslations, PanlexSource, PivotSupport, Synonyms,
+};
+use crate::model::{PanlexVariety, Sentence, TranslationsSet, WordTranslations};
+use crate::normalize::Normalization;
+use crate::panlex::{normalized_expr, prepare};
 use axum::http::StatusCode;
 use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
 use tracing::error;
 
+/// Whether `lang` is an ISO 639-3 code or a PanLex UID like "srp-001".
+pub fn is_lang_selector(lang: &str) -> bool {
+    let (iso3, var) = lang.split_once('-').unwrap_or((lang, "000"));
+    iso3.len() == 3
+        && iso3.bytes().all(|b| b.is_ascii_lowercase())
+        && var.len() == 3
+        && var.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// ISO 639-3 code of a language selector.
+pub(crate) fn iso3_of(lang: &str) -> &str {
+    lang.split('-').next().unwrap_or(lang)
+}
+
+/// IDs of the varieties selected by `langs`, as a comma-separated SQL list;
+/// `None` if there are none. Expressions and denotations are filtered by
+/// these, because `lv.uid = ? OR lv.lc = ?` keeps SQLite from using the
+/// indexes starting with `langvar`.
+pub(crate) async fn variety_ids(
+    db_pool: &SqlitePool,
+    langs: &[&str],
+) -> Result<Option<String>, (StatusCode, String)> {
+    let placeholders = (1..=langs.len())
+        .map(|i| format!("?{i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "SELECT lv FROM lv WHERE uid IN ({placeholders}) OR lc IN ({placeholders}) ORDER BY lv"
+    );
+    let mut q = sqlx::query_scalar::<_, i64>(&sql);
+    for lang in langs {
+        q = q.bind(*lang);
+    }
+    let ids = q.fetch_all(db_pool).await.map_err(|e| {
+        error!(error = %e, "failed to look up PanLex varieties");
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })?;
+    Ok((!ids.is_empty()).then(|| {
+        ids.iter()
+            .map(i64::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }))
+}
+
+/// What `prepare-panlex` precomputed, read once when the DB is opened: a
+/// running server needs a restart to use it.
+#[derive(Clone, Debug, Default)]
+pub struct Precomputed {
+    /// How `expr.txt_norm` was computed; `None` if it was not.
+    pub normalization: Option<Normalization>,
+    /// Language pairs (from, to) whose translations are precomputed
+    pub translation_pairs: HashSet<(String, String)>,
+}
+
+impl Precomputed {
+    pub async fn read(db_pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            normalization: normalized_expr::normalization(db_pool).await?,
+            translation_pairs: prepare::translation_pairs(db_pool).await?,
+        })
+    }
+
+    fn has_translation_pair(&self, lang_from: &str, lang_to: &str) -> bool {
+        self.translation_pairs
+            .contains(&(lang_from.to_string(), lang_to.to_string()))
+    }
+}
+
+/// How expressions are matched against a query: by its normalization key
+/// once `prepare-panlex --normalize` precomputed `expr.txt_norm`, exactly
+/// otherwise.
+struct QueryMatch {
+    normalized: bool,
+    /// Bound as `?2`
+    key: String,
+}
+
+impl QueryMatch {
+    fn new(precomputed: &Precomputed, query: &str, lang: &str) -> Self {
+        match precomputed.normalization {
+            Some(normalization) => Self {
+                normalized: true,
+                key: normalization.key(query, iso3_of(lang)),
+            },
+            None => Self {
+                normalized: false,
+                key: query.to_string(),
+            },
+        }
+    }
+
+    /// Condition on `ex`, an alias of the `ex` view.
+    fn condition(&self, ex: &str) -> String {
+        if self.normalized {
+            format!("{ex}.ex IN (SELECT id FROM expr WHERE txt_norm = ?2)")
+        } else {
+            format!("{ex}.tt = ?2")
+        }
+    }
+}
+
 pub async fn get_translations(
     db_pool: &SqlitePool,
+    precomputed: &Precomputed,
     query: &str,
     lang_from_iso3: &str,
     lang_to_iso3: &str,
 ) -> Result<Option<WordTranslations>, (StatusCode, String)> {
-    let uid_from = format!("{lang_from_iso3}-000");
-    let uid_to = format!("{lang_to_iso3}-000");
     let query = query.trim();
+    let query_match = QueryMatch::new(precomputed, query, lang_from_iso3);
 
-    let sql = r#"
+    let sql = if precomputed.has_translation_pair(lang_from_iso3, lang_to_iso3) {
+        format!(
+            r#"
+        SELECT
+          t.txt                      AS txt,
+          MAX(t.quality)             AS quality,
+          GROUP_CONCAT(t.source_ids) AS source_ids
+        FROM panlex_translation AS t
+        WHERE t.lang_from = ?1 AND t.lang_to = ?3
+          AND {matches_query}
+        GROUP BY t.txt
+        ORDER BY t.txt
+    "#,
+            matches_query = query_match.condition("t")
+        )
+    } else {
+        let (Some(from_ids), Some(to_ids)) = (
+            variety_ids(db_pool, &[lang_from_iso3]).await?,
+            variety_ids(db_pool, &[lang_to_iso3]).await?,
+        ) else {
+            return Ok(None);
+        };
+        format!(
+            r#"
         WITH src_meanings AS (
           SELECT dnx.mn
           FROM ex
-          JOIN lv   ON lv.lv = ex.lv
           JOIN dnx  ON dnx.ex = ex.ex
-          WHERE lv.uid = ?1
-            AND ex.tt = ?2
+          WHERE ex.lv IN ({from_ids})
+            AND {matches_query}
         )
         SELECT
           ex_ru.tt               AS txt,
-          MAX(COALESCE(d_ru.uq, 0)) AS quality
+          MAX(COALESCE(d_ru.uq, 0)) AS quality,
+          GROUP_CONCAT(DISTINCT d_ru.ap) AS source_ids
         FROM src_meanings
-        JOIN dnx  AS d_ru  ON d_ru.mn = src_meanings.mn
-        JOIN lv   AS lv_ru ON lv_ru.lv = d_ru.lv AND lv_ru.uid = ?3
+        JOIN dnx  AS d_ru  ON d_ru.mn = src_meanings.mn AND d_ru.lv IN ({to_ids})
         JOIN ex   AS ex_ru ON ex_ru.ex = d_ru.ex
         GROUP BY ex_ru.tt
         ORDER BY ex_ru.tt
-    "#;
+    "#,
+            matches_query = query_match.condition("ex")
+        )
+    };
 
-    let rows: Vec<(String, i64)> = sqlx::query_as::<_, (String, i64)>(sql)
-        .bind(&uid_from)
-        .bind(query)
-        .bind(&uid_to)
+    let rows: Vec<(String, i64, Option<String>)> = sqlx::query_as(&sql)
+        .bind(lang_from_iso3)
+        .bind(&query_match.key)
+        .bind(lang_to_iso3)
         .fetch_all(db_pool)
         .await
         .map_err(|e| {
@@ -53,58 +189,304 @@ pub async fn get_translations(
 
     let mut translations = Vec::with_capacity(rows.len());
     let mut qualities = Vec::with_capacity(rows.len());
-    for (txt, q) in rows {
-        translations.push(Sentence::new(txt, lang_to_iso3, &source));
+    let mut source_ids = Vec::with_capacity(rows.len());
+    for (txt, q, ids) in rows {
+        translations.push(Sentence::new(txt, iso3_of(lang_to_iso3), &source));
         // PanLex quality is 0–9; clamp to i32 just in case.
         let q = (q as i8).clamp(0, 9);
         qualities.push(q);
+        source_ids.push(ids);
     }
+    let translations_sources = get_sources(db_pool, &source_ids).await?;
 
     let ts = TranslationsSet {
-        original: Sentence::new(query, lang_from_iso3, &source),
+        original: Sentence::new(query, iso3_of(lang_from_iso3), &source),
         translations,
         translations_qualities: Some(qualities),
     };
 
     Ok(Some(WordTranslations {
         translations_set: ts,
+        translations_sources: Some(translations_sources),
+        source,
+    }))
+}
+
+/// Sources of each list of comma-separated source IDs, as aggregated by
+/// `GROUP_CONCAT`, best rated first.
+async fn get_sources(
+    db_pool: &SqlitePool,
+    source_ids: &[Option<String>],
+) -> Result<Vec<Vec<PanlexSource>>, (StatusCode, String)> {
+    let parse = |ids: &Option<String>| -> Vec<i64> {
+        let mut ids: Vec<i64> = ids
+            .iter()
+            .flat_map(|ids| ids.split(','))
+            .filter_map(|id| id.parse().ok())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    };
+    let mut all_ids: Vec<i64> = source_ids.iter().flat_map(parse).collect();
+    all_ids.sort_unstable();
+    all_ids.dedup();
+
+    let mut by_id = HashMap::new();
+    if !all_ids.is_empty() {
+        // The IDs are integers, so they can be inlined.
+        let sql = format!(
+            r#"
+            SELECT id, label, title, author, url, license, COALESCE(quality, 0)
+            FROM source
+            WHERE id IN ({})
+            "#,
+            all_ids
+                .iter()
+                .map(i64::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        type Row = (
+            i64,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            i64,
+        );
+        let rows: Vec<Row> = sqlx::query_as(&sql).fetch_all(db_pool).await.map_err(|e| {
+            error!(error = %e, "failed to execute PanLex sources query");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })?;
+        for (id, label, title, author, url, license, quality) in rows {
+            let source = PanlexSource {
+                label,
+                title,
+                author,
+                url,
+                license,
+                quality: quality as i32,
+            };
+            by_id.insert(id, source);
+        }
+    }
+
+    Ok(source_ids
+        .iter()
+        .map(|ids| {
+            let mut sources: Vec<PanlexSource> = parse(ids)
+                .iter()
+                .filter_map(|id| by_id.get(id).cloned())
+                .collect();
+            sources.sort_by(|a, b| {
+                b.quality
+                    .cmp(&a.quality)
+                    .then_with(|| a.label.cmp(&b.label))
+            });
+            sources
+        })
+        .collect())
+}
+
+/// Indirect translations are ranked by their support; the weakest are cut.
+const MAX_INDIRECT_TRANSLATIONS: i64 = 20;
+
+/// Pivot expressions translations are looked for through, best rated
+/// first: each one joins all the denotations of all its meanings.
+const MAX_PIVOT_EXPRESSIONS: i64 = 25;
+
+/// Translations of `query` reached through expressions in `pivot_langs_iso3`
+/// which share a meaning with `query` and, through another meaning, with the
+/// translation. Direct translations are left out.
+pub async fn get_indirect_translations(
+    db_pool: &SqlitePool,
+    precomputed: &Precomputed,
+    query: &str,
+    lang_from_iso3: &str,
+    lang_to_iso3: &str,
+    pivot_langs_iso3: &[String],
+) -> Result<Option<IndirectTranslations>, (StatusCode, String)> {
+    let pivots: Vec<&str> = pivot_langs_iso3
+        .iter()
+        .map(String::as_str)
+        .filter(|l| *l != lang_from_iso3 && *l != lang_to_iso3)
+        .collect();
+    if pivots.is_empty() {
+        return Ok(None);
+    }
+    let query = query.trim();
+    let query_match = QueryMatch::new(precomputed, query, lang_from_iso3);
+
+    let (Some(from_ids), Some(to_ids), Some(pivot_ids)) = (
+        variety_ids(db_pool, &[lang_from_iso3]).await?,
+        variety_ids(db_pool, &[lang_to_iso3]).await?,
+        variety_ids(db_pool, &pivots).await?,
+    ) else {
+        return Ok(None);
+    };
+    let sql = format!(
+        r#"
+        WITH src_meanings AS (
+          SELECT DISTINCT dnx.mn
+          FROM ex
+          JOIN dnx  ON dnx.ex = ex.ex
+          WHERE ex.lv IN ({from_ids})
+            AND {matches_query}
+        ),
+        direct AS (
+          SELECT d.ex
+          FROM src_meanings
+          JOIN dnx AS d ON d.mn = src_meanings.mn
+        ),
+        -- The best rated pivot expressions; common words have many more.
+        pivot_exprs AS (
+          SELECT d_pv.ex, lv_pv.lc, MAX(COALESCE(d_pv.uq, 0)) AS uq
+          FROM src_meanings
+          JOIN dnx  AS d_pv  ON d_pv.mn = src_meanings.mn AND d_pv.lv IN ({pivot_ids})
+          JOIN lv   AS lv_pv ON lv_pv.lv = d_pv.lv
+          GROUP BY d_pv.ex
+          ORDER BY uq DESC, d_pv.ex
+          LIMIT ?5
+        ),
+        pivots AS (
+          SELECT DISTINCT pivot_exprs.ex, pivot_exprs.lc, d_pv.ap
+          FROM pivot_exprs
+          JOIN dnx AS d_pv ON d_pv.ex = pivot_exprs.ex
+          JOIN src_meanings ON src_meanings.mn = d_pv.mn
+        ),
+        paths AS MATERIALIZED (
+          SELECT ex_to.tt, pivots.ex AS pivot, pivots.lc, pivots.ap AS ap_pv, d_to.ap AS ap_to, d_to.uq
+          FROM pivots
+          JOIN dnx  AS d_pv2 ON d_pv2.ex = pivots.ex
+          JOIN dnx  AS d_to  ON d_to.mn = d_pv2.mn AND d_to.lv IN ({to_ids})
+          JOIN ex   AS ex_to ON ex_to.ex = d_to.ex
+          WHERE d_to.ex NOT IN (SELECT ex FROM direct)
+        ),
+        support AS (
+          SELECT
+            tt,
+            COUNT(DISTINCT pivot)  AS paths,
+            GROUP_CONCAT(DISTINCT lc) AS via,
+            MAX(COALESCE(uq, 0))   AS quality
+          FROM paths
+          GROUP BY tt
+        ),
+        -- Sources of either link of a path
+        path_sources AS (
+          SELECT tt, ap_pv AS ap FROM paths
+          UNION
+          SELECT tt, ap_to FROM paths
+        ),
+        tt_sources AS (
+          SELECT tt, COUNT(*) AS sources, GROUP_CONCAT(ap) AS source_ids
+          FROM path_sources
+          GROUP BY tt
+        )
+        SELECT
+          support.tt AS txt,
+          support.paths,
+          tt_sources.sources,
+          support.via,
+          support.quality,
+          tt_sources.source_ids
+        FROM support
+        JOIN tt_sources ON tt_sources.tt = support.tt
+        ORDER BY support.paths DESC, tt_sources.sources DESC, support.quality DESC, support.tt
+        LIMIT ?4
+    "#,
+        matches_query = query_match.condition("ex")
+    );
+
+    type Row = (String, i64, i64, String, i64, Option<String>);
+    let rows = sqlx::query_as::<_, Row>(&sql)
+        .bind(lang_from_iso3)
+        .bind(&query_match.key)
+        .bind(lang_to_iso3)
+        .bind(MAX_INDIRECT_TRANSLATIONS)
+        .bind(MAX_PIVOT_EXPRESSIONS)
+        .fetch_all(db_pool)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "failed to execute PanLex pivot translation query");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })?;
+
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let source = "panlex".to_string();
+    let mut translations = Vec::with_capacity(rows.len());
+    let mut qualities = Vec::with_capacity(rows.len());
+    let mut support = Vec::with_capacity(rows.len());
+    let mut source_ids = Vec::with_capacity(rows.len());
+    for (txt, paths, sources, via, q, ids) in rows {
+        translations.push(Sentence::new(txt, iso3_of(lang_to_iso3), &source));
+        qualities.push((q as i8).clamp(0, 9));
+        let mut via_langs_iso3: Vec<String> = via.split(',').map(str::to_string).collect();
+        via_langs_iso3.sort();
+        support.push(PivotSupport {
+            paths: paths as i32,
+            sources: sources as i32,
+            via_langs_iso3,
+        });
+        source_ids.push(ids);
+    }
+    let translations_sources = get_sources(db_pool, &source_ids).await?;
+
+    Ok(Some(IndirectTranslations {
+        translations_set: TranslationsSet {
+            original: Sentence::new(query, iso3_of(lang_from_iso3), &source),
+            translations,
+            translations_qualities: Some(qualities),
+        },
+        support,
+        translations_sources,
         source,
     }))
 }
 
 pub async fn get_synonyms(
     db_pool: &SqlitePool,
+    precomputed: &Precomputed,
     query: &str,
     lang_from_iso3: &str,
 ) -> Result<Option<Synonyms>, (StatusCode, String)> {
-    let uid_from = format!("{lang_from_iso3}-000");
     let query = query.trim();
+    let query_match = QueryMatch::new(precomputed, query, lang_from_iso3);
+    let Some(ids) = variety_ids(db_pool, &[lang_from_iso3]).await? else {
+        return Ok(None);
+    };
 
-    let sql = r#"
+    let sql = format!(
+        r#"
         WITH src_expr AS (
           SELECT ex.ex AS src_ex, dnx.mn
           FROM ex
-          JOIN lv   ON lv.lv = ex.lv
           JOIN dnx  ON dnx.ex = ex.ex
-          WHERE lv.uid = ?1
-            AND ex.tt = ?2
+          WHERE ex.lv IN ({ids})
+            AND {matches_query}
         )
         SELECT
           ex_syn.tt                    AS txt,
           MAX(COALESCE(d_syn.uq, 0))   AS quality
         FROM src_expr
-        JOIN dnx  AS d_syn  ON d_syn.mn = src_expr.mn
-        JOIN lv   AS lv_syn ON lv_syn.lv = d_syn.lv AND lv_syn.uid = ?1
+        JOIN dnx  AS d_syn  ON d_syn.mn = src_expr.mn AND d_syn.lv IN ({ids})
         JOIN ex   AS ex_syn ON ex_syn.ex = d_syn.ex
         WHERE ex_syn.ex NOT IN (SELECT src_ex FROM src_expr)
-          AND ex_syn.tt <> ?2
+          AND NOT {matches_synonym}
         GROUP BY ex_syn.tt
         ORDER BY ex_syn.tt
-    "#;
+    "#,
+        matches_query = query_match.condition("ex"),
+        matches_synonym = query_match.condition("ex_syn")
+    );
 
-    let rows: Vec<(String, i64)> = sqlx::query_as::<_, (String, i64)>(sql)
-        .bind(&uid_from)
-        .bind(query)
+    let rows: Vec<(String, i64)> = sqlx::query_as::<_, (String, i64)>(&sql)
+        .bind(lang_from_iso3)
+        .bind(&query_match.key)
         .fetch_all(db_pool)
         .await
         .map_err(|e| {
@@ -120,13 +502,13 @@ pub async fn get_synonyms(
     let mut syns = Vec::with_capacity(rows.len());
     let mut quals = Vec::with_capacity(rows.len());
     for (txt, q) in rows {
-        syns.push(Sentence::new(txt, lang_from_iso3, &source));
+        syns.push(Sentence::new(txt, iso3_of(lang_from_iso3), &source));
         let q = (q as i8).clamp(0, 9);
         quals.push(q);
     }
 
     let ts = TranslationsSet {
-        original: Sentence::new(query, lang_from_iso3, &source),
+        original: Sentence::new(query, iso3_of(lang_from_iso3), &source),
         translations: syns,
         translations_qualities: Some(quals),
     };
@@ -137,13 +519,64 @@ pub async fn get_synonyms(
     }))
 }
 
+/// Varieties of `lang_iso3`, by variety code.
+pub async fn get_varieties(
+    db_pool: &SqlitePool,
+    lang_iso3: &str,
+) -> Result<Vec<PanlexVariety>, (StatusCode, String)> {
+    let sql = r#"
+        SELECT uid, lc, vc, tt, rgtt, sctt
+        FROM lv
+        WHERE lc = ?1
+        ORDER BY vc
+    "#;
+    type Row = (
+        String,
+        String,
+        i64,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    );
+    let rows: Vec<Row> = sqlx::query_as(sql)
+        .bind(lang_iso3)
+        .fetch_all(db_pool)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "failed to execute PanLex varieties query");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(uid, lang_iso3, var_code, name, region, script)| PanlexVariety {
+                uid,
+                lang_iso3,
+                var_code: var_code as i32,
+                name,
+                region,
+                script,
+            },
+        )
+        .collect())
+}
+
 #[cfg(test)]
-mod tests {
-    use crate::model::lexical_item_detail::Synonyms;
-    use crate::model::{Sentence, TranslationsSet, WordTranslations};
+pub(crate) mod tests {
+    use super::{
+        MAX_INDIRECT_TRANSLATIONS, MAX_PIVOT_EXPRESSIONS, Precomputed, get_synonyms,
+        get_translations,
+    };
+    use crate::model::lexical_item_detail::{
+        IndirectTranslations, PanlexSource, PivotSupport, Synonyms,
+    };
+    use crate::model::{PanlexVariety, Sentence, TranslationsSet, WordTranslations};
+    use crate::normalize::Normalization;
+    use crate::panlex::normalized_expr;
     use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
 
-    async fn new_test_pool() -> SqlitePool {
+    pub(crate) async fn new_test_pool() -> SqlitePool {
         let pool = SqlitePoolOptions::new()
             .max_connections(1) // <- important for :memory:
             .connect("sqlite::memory:")
@@ -244,10 +677,11 @@ CREATE INDEX denotationx_langvar ON denotationx (langvar);
         .await
         .unwrap();
 
-        let result = super::get_translations(&pool, " Imker ", "deu", "eng")
-            .await
-            .expect("ok")
-            .expect("some");
+        let result =
+            super::get_translations(&pool, &Precomputed::default(), " Imker ", "deu", "eng")
+                .await
+                .expect("ok")
+                .expect("some");
 
         let source = "panlex".to_string();
         let expected = WordTranslations {
@@ -259,21 +693,394 @@ CREATE INDEX denotationx_langvar ON denotationx (langvar);
                 ],
                 translations_qualities: Some(vec![9_i8, 5_i8]),
             },
+            translations_sources: Some(vec![vec![], vec![]]),
             source: source.clone(),
         };
 
         assert_eq!(result, expected);
     }
 
+    #[tokio::test]
+    async fn translations_are_attributed_to_their_sources() {
+        let pool = new_test_pool().await;
+        sqlx::query(
+            r#"-- noinspection SqlNoDataSourceInspectionForFile
+            INSERT INTO langvar(id, lang_code, var_code, uid) VALUES
+              (100,'deu',0,'deu-000'),
+              (200,'fin',0,'fin-000'),
+              (300,'eng',0,'eng-000');
+            INSERT INTO expr(id, langvar, txt) VALUES
+              (1000,100,'Imker'),
+              (2000,200,'mehiläishoitaja'),
+              (3000,300,'beekeeper'),
+              (3001,300,'apiarist');
+            INSERT INTO source(id, label, title, author, url, license, quality) VALUES
+              (1, 'deu-eng:Wahrig', 'Wörterbuch', 'Wahrig', NULL, 'copyright', 4),
+              (2, 'mul:Wiktionary', 'Wiktionary', NULL, 'https://wiktionary.org', 'cc-by-sa', 6),
+              (3, 'eng-fin:Sanakirja', NULL, NULL, NULL, NULL, 2);
+            INSERT INTO denotationx(meaning, source, grp, quality, expr, langvar) VALUES
+              (1, 1, 1, 7, 1000, 100),  -- meaning 1, Wahrig: Imker = beekeeper, apiarist
+              (1, 1, 1, 5, 3000, 300),
+              (1, 1, 1, 5, 3001, 300),
+              (2, 2, 1, 7, 1000, 100),  -- meaning 2, Wiktionary: Imker = beekeeper
+              (2, 2, 1, 5, 3000, 300),
+              (3, 3, 1, 5, 3000, 300),  -- meaning 3, Sanakirja: beekeeper = mehiläishoitaja
+              (3, 3, 1, 5, 2000, 200),
+              (3, 4, 1, 5, 2000, 200);  -- a source missing from the source table
+        "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let wahrig = PanlexSource {
+            label: "deu-eng:Wahrig".into(),
+            title: Some("Wörterbuch".into()),
+            author: Some("Wahrig".into()),
+            url: None,
+            license: Some("copyright".into()),
+            quality: 4,
+        };
+        let wiktionary = PanlexSource {
+            label: "mul:Wiktionary".into(),
+            title: Some("Wiktionary".into()),
+            author: None,
+            url: Some("https://wiktionary.org".into()),
+            license: Some("cc-by-sa".into()),
+            quality: 6,
+        };
+        let sanakirja = PanlexSource {
+            label: "eng-fin:Sanakirja".into(),
+            title: None,
+            author: None,
+            url: None,
+            license: None,
+            quality: 2,
+        };
+
+        // Direct: "apiarist", then "beekeeper", best rated source first
+        let direct = super::get_translations(&pool, &Precomputed::default(), "Imker", "deu", "eng")
+            .await
+            .expect("ok")
+            .expect("some");
+        assert_eq!(
+            direct.translations_sources,
+            Some(vec![
+                vec![wahrig.clone()],
+                vec![wiktionary.clone(), wahrig.clone()],
+            ])
+        );
+
+        // Indirect: both links of the paths through "beekeeper"
+        let pivots = ["eng".to_string()];
+        let indirect = super::get_indirect_translations(
+            &pool,
+            &Precomputed::default(),
+            "Imker",
+            "deu",
+            "fin",
+            &pivots,
+        )
+        .await
+        .expect("ok")
+        .expect("some");
+        assert_eq!(
+            indirect.translations_set.translations,
+            vec![Sentence::new("mehiläishoitaja", "fin", "panlex")]
+        );
+        assert_eq!(
+            indirect.translations_sources,
+            vec![vec![wiktionary, wahrig, sanakirja]]
+        );
+    }
+
+    #[tokio::test]
+    async fn varieties_are_selected_by_uid_or_language() {
+        let pool = new_test_pool().await;
+        sqlx::query(
+            r#"-- noinspection SqlNoDataSourceInspectionForFile
+            INSERT INTO langvar(id, lang_code, var_code, uid, name_expr_txt, region_expr_txt, script_expr_txt) VALUES
+              (100,'deu',0,'deu-000','Deutsch',NULL,'Latn'),
+              (200,'srp',0,'srp-000','српски',NULL,'Cyrl'),
+              (201,'srp',1,'srp-001','srpski',NULL,'Latn');
+            INSERT INTO expr(id, langvar, txt) VALUES
+              (1000,100,'Hund'),
+              (2000,200,'пас'),
+              (2010,201,'pas');
+            INSERT INTO denotationx(meaning, source, grp, quality, expr, langvar) VALUES
+              (1, 1, 1, 5, 1000, 100),
+              (1, 1, 1, 5, 2000, 200),
+              (1, 1, 1, 5, 2010, 201);
+        "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let texts = |wt: WordTranslations| {
+            wt.translations_set
+                .translations
+                .into_iter()
+                .map(|s| (s.text, s.lang_iso3))
+                .collect::<Vec<_>>()
+        };
+        let all = super::get_translations(&pool, &Precomputed::default(), "Hund", "deu", "srp")
+            .await
+            .expect("ok")
+            .expect("some");
+        assert_eq!(
+            texts(all),
+            vec![("pas".into(), "srp".into()), ("пас".into(), "srp".into())]
+        );
+        let latin =
+            super::get_translations(&pool, &Precomputed::default(), "Hund", "deu-000", "srp-001")
+                .await
+                .expect("ok")
+                .expect("some");
+        assert_eq!(texts(latin), vec![("pas".into(), "srp".into())]);
+
+        let varieties = super::get_varieties(&pool, "srp").await.expect("ok");
+        assert_eq!(
+            varieties[1],
+            PanlexVariety {
+                uid: "srp-001".into(),
+                lang_iso3: "srp".into(),
+                var_code: 1,
+                name: Some("srpski".into()),
+                region: None,
+                script: Some("Latn".into()),
+            }
+        );
+        assert_eq!(varieties.len(), 2);
+        assert!(super::is_lang_selector("srp-001"));
+        assert!(super::is_lang_selector("srp"));
+        assert!(!super::is_lang_selector("srp-1"));
+        assert!(!super::is_lang_selector("sr"));
+    }
+
     #[tokio::test]
     async fn translations_return_none_when_no_match() {
         let pool = new_test_pool().await;
-        let out = super::get_translations(&pool, "Nope", "deu", "eng")
+        let out = super::get_translations(&pool, &Precomputed::default(), "Nope", "deu", "eng")
             .await
             .expect("ok");
         assert_eq!(out, None);
     }
 
+    #[tokio::test]
+    async fn indirect_translations_are_ranked_by_support() {
+        let pool = new_test_pool().await;
+        sqlx::query(
+            r#"-- noinspection SqlNoDataSourceInspectionForFile
+            INSERT INTO langvar(id, lang_code, var_code, uid) VALUES
+              (100,'deu',0,'deu-000'),
+              (200,'fin',0,'fin-000'),
+              (300,'eng',0,'eng-000'),
+              (400,'spa',0,'spa-000');
+            INSERT INTO expr(id, langvar, txt) VALUES
+              (1000,100,'Imker'),
+              (2000,200,'mehiläishoitaja'),
+              (2001,200,'tarhaaja'),
+              (2002,200,'suora'),
+              (3000,300,'beekeeper'),
+              (4000,400,'apicultor');
+            INSERT INTO denotationx(meaning, source, grp, quality, expr, langvar) VALUES
+              (1, 1, 1, 7, 1000, 100),  -- meaning 1: Imker = beekeeper = apicultor
+              (1, 1, 1, 7, 3000, 300),
+              (1, 2, 1, 7, 4000, 400),
+              (1, 3, 1, 7, 2002, 200),  -- direct translation, not repeated
+              (2, 4, 1, 6, 3000, 300),  -- meaning 2: beekeeper = mehiläishoitaja
+              (2, 4, 1, 6, 2000, 200),
+              (3, 5, 1, 5, 4000, 400),  -- meaning 3: apicultor = mehiläishoitaja
+              (3, 5, 1, 5, 2000, 200),
+              (4, 6, 1, 4, 3000, 300),  -- meaning 4: beekeeper = tarhaaja
+              (4, 6, 1, 4, 2001, 200);
+        "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let pivots = ["eng".to_string(), "spa".to_string(), "deu".to_string()];
+        let result = super::get_indirect_translations(
+            &pool,
+            &Precomputed::default(),
+            "Imker",
+            "deu",
+            "fin",
+            &pivots,
+        )
+        .await
+        .expect("ok")
+        .expect("some");
+
+        let source = "panlex".to_string();
+        assert_eq!(
+            result,
+            IndirectTranslations {
+                translations_set: TranslationsSet {
+                    original: Sentence::new("Imker", "deu", &source),
+                    translations: vec![
+                        Sentence::new("mehiläishoitaja", "fin", &source),
+                        Sentence::new("tarhaaja", "fin", &source),
+                    ],
+                    translations_qualities: Some(vec![6, 4]),
+                },
+                support: vec![
+                    PivotSupport {
+                        paths: 2,
+                        sources: 4,
+                        via_langs_iso3: vec!["eng".into(), "spa".into()],
+                    },
+                    PivotSupport {
+                        paths: 1,
+                        sources: 2,
+                        via_langs_iso3: vec!["eng".into()],
+                    },
+                ],
+                translations_sources: vec![vec![], vec![]],
+                source,
+            }
+        );
+
+        let none = super::get_indirect_translations(
+            &pool,
+            &Precomputed::default(),
+            "Imker",
+            "deu",
+            "fin",
+            &[],
+        )
+        .await
+        .expect("ok");
+        assert_eq!(none, None);
+    }
+
+    #[tokio::test]
+    async fn indirect_translation_sources_count_both_links() {
+        let pool = new_test_pool().await;
+        sqlx::query(
+            r#"-- noinspection SqlNoDataSourceInspectionForFile
+            INSERT INTO langvar(id, lang_code, var_code, uid) VALUES
+              (100,'deu',0,'deu-000'),
+              (200,'fin',0,'fin-000'),
+              (300,'eng',0,'eng-000');
+            INSERT INTO expr(id, langvar, txt) VALUES
+              (1000,100,'Imker'),
+              (2000,200,'mehiläishoitaja'),
+              (2001,200,'tarhaaja'),
+              (3000,300,'beekeeper'),
+              (3001,300,'apiarist');
+            INSERT INTO denotationx(meaning, source, grp, quality, expr, langvar) VALUES
+              (1, 1, 1, 5, 1000, 100),  -- meaning 1, source 1: Imker = beekeeper
+              (1, 1, 1, 5, 3000, 300),
+              (2, 1, 1, 5, 3000, 300),  -- meaning 2, source 1: beekeeper = tarhaaja
+              (2, 1, 1, 5, 2001, 200),
+              (3, 2, 1, 5, 1000, 100),  -- meaning 3, source 2: Imker = apiarist
+              (3, 2, 1, 5, 3001, 300),
+              (4, 3, 1, 5, 3001, 300),  -- meaning 4, source 3: apiarist = mehiläishoitaja
+              (4, 3, 1, 5, 2000, 200);
+        "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let pivots = ["eng".to_string()];
+        let result = super::get_indirect_translations(
+            &pool,
+            &Precomputed::default(),
+            "Imker",
+            "deu",
+            "fin",
+            &pivots,
+        )
+        .await
+        .expect("ok")
+        .expect("some");
+
+        // One path each, but only the links to "mehiläishoitaja" come from
+        // different sources.
+        let source = "panlex".to_string();
+        assert_eq!(
+            result.translations_set.translations,
+            vec![
+                Sentence::new("mehiläishoitaja", "fin", &source),
+                Sentence::new("tarhaaja", "fin", &source),
+            ]
+        );
+        assert_eq!(
+            result
+                .support
+                .iter()
+                .map(|s| (s.paths, s.sources))
+                .collect::<Vec<_>>(),
+            vec![(1, 2), (1, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn only_the_best_rated_pivot_expressions_are_followed() {
+        let pool = new_test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO langvar(id, lang_code, var_code, uid) VALUES
+              (100,'deu',0,'deu-000'),
+              (200,'fin',0,'fin-000'),
+              (300,'eng',0,'eng-000');
+            INSERT INTO expr(id, langvar, txt) VALUES (1000,100,'Imker');
+            INSERT INTO denotationx(meaning, source, grp, quality, expr, langvar) VALUES
+              (1, 1, 1, 5, 1000, 100);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // Imker = pivot i (rated i) = fin i (rated n - i): the translations
+        // through the weakest pivots would rank first if they were followed.
+        let n = MAX_PIVOT_EXPRESSIONS + 5;
+        for i in 0..n {
+            sqlx::query(
+                r#"
+                INSERT INTO expr(id, langvar, txt) VALUES (?1, 300, 'pivot' || ?1), (?2, 200, 'fin' || ?1);
+                INSERT INTO denotationx(meaning, source, grp, quality, expr, langvar) VALUES
+                  (1, 1, 1, ?1, ?1, 300),
+                  (?2, 1, 1, ?1, ?1, 300),
+                  (?2, 1, 1, ?3, ?2, 200);
+                "#,
+            )
+            .bind(i)
+            .bind(10_000 + i)
+            .bind(n - i)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let pivots = ["eng".to_string()];
+        let result = super::get_indirect_translations(
+            &pool,
+            &Precomputed::default(),
+            "Imker",
+            "deu",
+            "fin",
+            &pivots,
+        )
+        .await
+        .expect("ok")
+        .expect("some");
+        let texts: Vec<&str> = result
+            .translations_set
+            .translations
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(texts.len(), MAX_INDIRECT_TRANSLATIONS as usize);
+        for i in 0..n - MAX_PIVOT_EXPRESSIONS {
+            assert!(!texts.contains(&format!("fin{i}").as_str()), "{texts:?}");
+        }
+    }
+
     #[tokio::test]
     async fn synonyms_happy_path_same_language() {
         let pool = new_test_pool().await;
@@ -296,7 +1103,7 @@ CREATE INDEX denotationx_langvar ON denotationx (langvar);
         .await
         .unwrap();
 
-        let result = super::get_synonyms(&pool, " Imker ", "deu")
+        let result = super::get_synonyms(&pool, &Precomputed::default(), " Imker ", "deu")
             .await
             .expect("ok")
             .expect("some");
@@ -317,6 +1124,62 @@ CREATE INDEX denotationx_langvar ON denotationx (langvar);
         assert_eq!(result, expected);
     }
 
+    #[tokio::test]
+    async fn normalized_spellings_match_once_precomputed() {
+        let pool = new_test_pool().await;
+        sqlx::query(
+            r#"-- noinspection SqlNoDataSourceInspectionForFile
+            INSERT INTO langvar(id, lang_code, var_code, uid) VALUES
+              (100,'deu',0,'deu-000'),
+              (300,'eng',0,'eng-000');
+            INSERT INTO expr(id, langvar, txt) VALUES
+              (1000,100,'Straße'),
+              (1001,100,'Strasse'),
+              (1002,100,'Gasse'),
+              (3000,300,'street');
+            INSERT INTO denotationx(meaning, source, grp, quality, expr, langvar) VALUES
+              (9999, 1, 1, 5, 1000, 100),
+              (9999, 1, 1, 5, 1001, 100),
+              (9999, 1, 1, 5, 1002, 100),
+              (9999, 1, 1, 5, 3000, 300);
+        "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let exact = Precomputed::default();
+        assert!(
+            get_translations(&pool, &exact, "STRASSE", "deu", "eng")
+                .await
+                .expect("ok")
+                .is_none()
+        );
+
+        normalized_expr::build(&pool, Normalization::default())
+            .await
+            .expect("ok");
+        let precomputed = Precomputed::read(&pool).await.expect("ok");
+        let translations = get_translations(&pool, &precomputed, "STRASSE", "deu", "eng")
+            .await
+            .expect("ok")
+            .expect("some");
+        assert_eq!(translations.translations_set.original.text, "STRASSE");
+        assert_eq!(translations.translations_set.translations[0].text, "street");
+
+        // Both spellings of the query are left out of its synonyms.
+        let synonyms = get_synonyms(&pool, &precomputed, "straße", "deu")
+            .await
+            .expect("ok")
+            .expect("some");
+        let synonyms: Vec<&str> = synonyms
+            .translations_set
+            .translations
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(synonyms, vec!["Gasse"]);
+    }
+
     #[tokio::test]
     async fn synonyms_none_when_only_source_exists() {
         let pool = new_test_pool().await;
@@ -334,7 +1197,7 @@ CREATE INDEX denotationx_langvar ON denotationx (langvar);
         .await
         .unwrap();
 
-        let result = super::get_synonyms(&pool, "Imker", "deu")
+        let result = super::get_synonyms(&pool, &Precomputed::default(), "Imker", "deu")
             .await
             .expect("ok");
 
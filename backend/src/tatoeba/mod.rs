@@ -1 +1,4 @@
+pub(crate) mod tatoeba_client;
+pub(crate) mod tatoeba_lexical_items;
 pub mod tatoeba_proxy;
+pub(crate) mod tatoeba_store;
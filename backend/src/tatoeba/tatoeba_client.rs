@@ -0,0 +1,199 @@
+use crate::model::Sentence;
+use crate::upstream::source_error::SourceError;
+use crate::upstream::upstream_proxy::Upstream;
+use crate::util::truncate;
+use axum::http::StatusCode;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use tracing::error;
+
+const DEFAULT_URL: &str = "https://tatoeba.org/en/api_v0/search";
+
+/// Tatoeba returns 10 sentences per page; more pages are fetched up to this.
+pub const MAX_LIMIT: usize = 50;
+
+/// Sentences in `from` containing `query`, with translations into `to`.
+pub struct TatoebaSearch<'a> {
+    pub query: &'a str,
+    pub from: &'a str,
+    pub to: &'a str,
+    pub limit: usize,
+}
+
+/// Runs `params` against `api_v0/search`, following pages until `limit`
+/// sentences are found or there are no more. Pages are fetched through
+/// `upstream`.
+pub async fn search(
+    http_client: &Client,
+    upstream: &Upstream,
+    params: &TatoebaSearch<'_>,
+    url: Option<&str>,
+) -> Result<Vec<TatoebaSentence>, SourceError> {
+    let url = url.unwrap_or(DEFAULT_URL);
+    let limit = params.limit.clamp(1, MAX_LIMIT);
+    let mut out = Vec::new();
+    let mut page = 1;
+    loop {
+        let page_url = Url::parse_with_params(
+            url,
+            [
+                ("query", params.query.trim()),
+                ("from", params.from),
+                ("to", params.to),
+                ("page", &page.to_string()),
+            ],
+        )
+        .map_err(|e| SourceError::Internal(e.to_string()))?;
+        let res = upstream.get(http_client, page_url).await?;
+
+        if !res.status.is_success() {
+            let body = String::from_utf8_lossy(&res.body);
+            error!(status = %res.status, body = %truncate(&body), "Tatoeba non-success");
+            return Err(SourceError::Upstream(res.status, body.into_owned()));
+        }
+
+        let parsed: TatoebaSearchResponse = serde_json::from_slice(&res.body).map_err(|e| {
+            error!(error = %e, "failed to deserialize Tatoeba response");
+            SourceError::Upstream(StatusCode::BAD_GATEWAY, e.to_string())
+        })?;
+
+        let has_next = parsed.paging.sentences.is_some_and(|p| p.next_page);
+        let empty = parsed.results.is_empty();
+        out.extend(parsed.results);
+        if limit <= out.len() || !has_next || empty {
+            out.truncate(limit);
+            return Ok(out);
+        }
+        page += 1;
+    }
+}
+
+#[derive(Deserialize)]
+struct TatoebaSearchResponse {
+    #[serde(default)]
+    paging: TatoebaPaging,
+    #[serde(default)]
+    results: Vec<TatoebaSentence>,
+}
+
+#[derive(Deserialize, Default)]
+struct TatoebaPaging {
+    #[serde(rename = "Sentences")]
+    sentences: Option<TatoebaPage>,
+}
+
+#[derive(Deserialize)]
+struct TatoebaPage {
+    #[serde(rename = "nextPage", default)]
+    next_page: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TatoebaSentence {
+    pub id: i64,
+    pub text: String,
+    pub lang: Option<String>,
+    pub user: Option<TatoebaUser>,
+    pub license: Option<String>,
+    /// Direct translations first, then indirect ones.
+    #[serde(default)]
+    pub translations: Vec<Vec<TatoebaSentence>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TatoebaUser {
+    pub username: String,
+}
+
+impl TatoebaSentence {
+    /// `lang_iso3` is used when Tatoeba does not know the language.
+    pub fn to_sentence(&self, lang_iso3: &str, source: &str) -> Sentence {
+        Sentence {
+            source_id: Some(self.id.to_string()),
+            owner: self.user.as_ref().map(|u| u.username.clone()),
+            license: self.license.clone(),
+            ..Sentence::new(
+                self.text.clone(),
+                self.lang.as_deref().unwrap_or(lang_iso3),
+                source,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tatoeba::tatoeba_proxy::UPSTREAM;
+    use crate::upstream::circuit_breaker::UpstreamBreakers;
+    use mockito::{Matcher, Server};
+
+    fn upstream() -> Upstream {
+        Upstream::new(UPSTREAM, UpstreamBreakers::default().tatoeba)
+    }
+
+    fn page(ids: std::ops::Range<i64>, next_page: bool) -> String {
+        let results: Vec<_> = ids
+            .map(|id| serde_json::json!({ "id": id, "text": format!("Satz {id}"), "lang": "deu" }))
+            .collect();
+        serde_json::json!({
+            "paging": { "Sentences": { "page": 1, "nextPage": next_page } },
+            "results": results,
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn pages_are_followed_up_to_limit() {
+        let mut server = Server::new_async().await;
+        let first = server
+            .mock("GET", "/search")
+            .match_query(Matcher::UrlEncoded("page".into(), "1".into()))
+            .with_body(page(0..10, true))
+            .create();
+        let second = server
+            .mock("GET", "/search")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_body(page(10..20, true))
+            .create();
+
+        let url = format!("{}/search", server.url());
+        let params = TatoebaSearch {
+            query: "Satz",
+            from: "deu",
+            to: "eng",
+            limit: 15,
+        };
+        let sentences = search(&Client::new(), &upstream(), &params, Some(&url))
+            .await
+            .expect("Ok");
+
+        assert_eq!(sentences.len(), 15);
+        assert_eq!(sentences[14].id, 14);
+        first.assert();
+        second.assert();
+    }
+
+    #[tokio::test]
+    async fn last_page_stops_paging() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/search")
+            .match_query(Matcher::Any)
+            .with_body(page(0..3, false))
+            .expect(1)
+            .create();
+
+        let url = format!("{}/search", server.url());
+        let params = TatoebaSearch {
+            query: "Satz",
+            from: "deu",
+            to: "eng",
+            limit: 10,
+        };
+        let sentences = search(&Client::new(), &upstream(), &params, Some(&url))
+            .await
+            .expect("Ok");
+        assert_eq!(sentences.len(), 3);
+    }
+}
@@ -0,0 +1,202 @@
+use super::tatoeba_client::{self, TatoebaSearch};
+use super::tatoeba_store::TatoebaStore;
+use crate::app_state::LocalDb;
+use crate::model::{LexicalItemDetail, TranslationsSet, lexical_item_detail::Example};
+use crate::upstream::source_error::SourceError;
+use crate::upstream::upstream_proxy::Upstream;
+use reqwest::Client;
+use tracing::error;
+
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Examples of sentences containing `search.query`, together with their
+/// translations. Sentence IDs, owners and licenses are kept for attribution.
+///
+/// The sentences come from the `local` DB when there is one. Tatoeba is
+/// searched through `upstream` when there is no local DB, or when it has no
+/// matches and falls back to Tatoeba.
+pub async fn get(
+    http_client: &Client,
+    upstream: &Upstream,
+    local: Option<&LocalDb<TatoebaStore>>,
+    search: &TatoebaSearch<'_>,
+    url: Option<&str>,
+) -> Result<Vec<Example>, SourceError> {
+    if let Some(LocalDb {
+        store,
+        live_fallback,
+    }) = local
+    {
+        let examples = store
+            .search(search.query, search.from, search.to, search.limit)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "failed to search local Tatoeba DB");
+                SourceError::Internal(e.to_string())
+            })?;
+        if !examples.is_empty() || !live_fallback {
+            return Ok(examples);
+        }
+    }
+    get_live(http_client, upstream, search, url).await
+}
+
+async fn get_live(
+    http_client: &Client,
+    upstream: &Upstream,
+    search: &TatoebaSearch<'_>,
+    url: Option<&str>,
+) -> Result<Vec<Example>, SourceError> {
+    let sentences = tatoeba_client::search(http_client, upstream, search, url).await?;
+
+    let source = "tatoeba";
+    let out = sentences
+        .into_iter()
+        .map(|r| {
+            let translations = r
+                .translations
+                .iter()
+                .flatten()
+                .filter(|t| t.lang.as_deref() == Some(search.to))
+                .map(|t| t.to_sentence(search.to, source))
+                .collect();
+            Example {
+                translations_set: TranslationsSet {
+                    original: r.to_sentence(search.from, source),
+                    translations,
+                    translations_qualities: None,
+                },
+                source: source.to_string(),
+            }
+        })
+        .collect();
+    Ok(out)
+}
+
+/// [`get`] as lexical item details, for lookups mixing sources.
+pub async fn get_details(
+    http_client: &Client,
+    upstream: &Upstream,
+    local: Option<&LocalDb<TatoebaStore>>,
+    query: &str,
+    lang_from_iso3: &str,
+    lang_to_iso3: &str,
+    url: Option<&str>,
+) -> Result<Vec<LexicalItemDetail>, SourceError> {
+    let search = TatoebaSearch {
+        query: query.trim(),
+        from: lang_from_iso3,
+        to: lang_to_iso3,
+        limit: DEFAULT_LIMIT,
+    };
+    let examples = get(http_client, upstream, local, &search, url).await?;
+    Ok(examples
+        .into_iter()
+        .map(LexicalItemDetail::Example)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Sentence;
+    use crate::tatoeba::tatoeba_proxy::UPSTREAM;
+    use crate::upstream::circuit_breaker::UpstreamBreakers;
+    use axum::http::StatusCode;
+    use mockito::{Matcher, Server};
+
+    fn upstream() -> Upstream {
+        Upstream::new(UPSTREAM, UpstreamBreakers::default().tatoeba)
+    }
+
+    const SEARCH_JSON: &str = r#"
+    {
+      "paging": {},
+      "results": [
+        {
+          "id": 1,
+          "text": "Der Hund bellt.",
+          "lang": "deu",
+          "license": "CC BY 2.0 FR",
+          "user": { "username": "hans" },
+          "translations": [
+            [ { "id": 2, "text": "The dog barks.", "lang": "eng" } ],
+            [ { "id": 3, "text": "Le chien aboie.", "lang": "fra" } ]
+          ]
+        }
+      ]
+    }"#;
+
+    #[tokio::test]
+    async fn results_become_examples() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), "Hund".into()),
+                Matcher::UrlEncoded("from".into(), "deu".into()),
+                Matcher::UrlEncoded("to".into(), "eng".into()),
+            ]))
+            .with_status(200)
+            .with_body(SEARCH_JSON)
+            .create();
+
+        let url = format!("{}/search", server.url());
+        let items = get_details(
+            &Client::new(),
+            &upstream(),
+            None,
+            " Hund ",
+            "deu",
+            "eng",
+            Some(&url),
+        )
+        .await
+        .expect("Ok");
+
+        let source = "tatoeba".to_string();
+        assert_eq!(
+            items,
+            vec![LexicalItemDetail::Example(Example {
+                translations_set: TranslationsSet {
+                    original: Sentence {
+                        source_id: Some("1".into()),
+                        owner: Some("hans".into()),
+                        license: Some("CC BY 2.0 FR".into()),
+                        ..Sentence::new("Der Hund bellt.", "deu", &source)
+                    },
+                    translations: vec![Sentence {
+                        source_id: Some("2".into()),
+                        ..Sentence::new("The dog barks.", "eng", &source)
+                    }],
+                    translations_qualities: None,
+                },
+                source: source.clone(),
+            })]
+        );
+    }
+
+    #[tokio::test]
+    async fn upstream_non_2xx_is_bad_gateway() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/search")
+            .match_query(Matcher::Any)
+            .with_status(503)
+            .create();
+
+        let url = format!("{}/search", server.url());
+        let err = get_details(
+            &Client::new(),
+            &upstream(),
+            None,
+            "Hund",
+            "deu",
+            "eng",
+            Some(&url),
+        )
+        .await
+        .expect_err("Err");
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -1,72 +1,280 @@
 use axum::{
     body::Body,
-    extract::{Query, State},
-    http::{header, Response, StatusCode},
+    extract::{Query, State, rejection::QueryRejection},
+    http::Response,
+    response::IntoResponse,
 };
-use std::collections::HashMap;
-use tracing::error;
+use reqwest::Url;
+use serde::Deserialize;
+use std::time::Duration;
 
 use crate::app_state::AppState;
+use crate::upstream::{proxy_error::ProxyError, upstream_proxy::UpstreamConfig};
 
-pub async fn tatoeba_proxy(
-    State(state): State<AppState>,
-    Query(mut params): Query<HashMap<String, String>>,
-) -> Result<Response<Body>, (StatusCode, String)> {
-    let query = match params.get_mut("query") {
-        Some(q) => {
-            let trimmed = q.trim().to_string();
-            if trimmed.is_empty() {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    "query must not be empty".into(),
-                ));
-            }
-            *q = trimmed.clone();
-            trimmed
+const URL: &str = "https://tatoeba.org/en/api_v0/search";
+
+/// Searches are slow and their results change as sentences are added.
+pub const UPSTREAM: UpstreamConfig = UpstreamConfig {
+    name: "Tatoeba",
+    error_code: "UPSTREAM_TATOEBA",
+    timeout: Duration::from_secs(15),
+    retries: 2,
+    backoff: Duration::from_millis(300),
+    default_max_age: Duration::from_secs(60 * 60),
+    cache_bytes: 32 * 1024 * 1024,
+};
+
+/// The `api_v0/search` parameters clients may pass on; anything else is
+/// rejected.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TatoebaProxyQuery {
+    pub query: String,
+    /// ISO 639-3 code of the sentences
+    pub from: Option<String>,
+    /// ISO 639-3 code the sentences must be translated into
+    pub to: Option<String>,
+    pub orphans: Option<YesNo>,
+    pub unapproved: Option<YesNo>,
+    pub has_audio: Option<YesNo>,
+    pub user: Option<String>,
+    pub word_count_min: Option<u32>,
+    pub word_count_max: Option<u32>,
+    pub sort: Option<Sort>,
+    pub sort_reverse: Option<YesNo>,
+    /// Starting at 1
+    pub page: Option<u32>,
+    pub trans_filter: Option<TransFilter>,
+    /// ISO 639-3 code of the translations `trans_*` apply to
+    pub trans_to: Option<String>,
+    pub trans_link: Option<TransLink>,
+    pub trans_has_audio: Option<YesNo>,
+    pub trans_unapproved: Option<YesNo>,
+    pub trans_orphan: Option<YesNo>,
+    pub trans_user: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum YesNo {
+    Yes,
+    No,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    Relevance,
+    Words,
+    Created,
+    Modified,
+    Random,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransFilter {
+    Limit,
+    Exclude,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransLink {
+    Direct,
+    Indirect,
+}
+
+impl YesNo {
+    fn as_str(self) -> &'static str {
+        match self {
+            YesNo::Yes => "yes",
+            YesNo::No => "no",
         }
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "missing required parameter `query`".into(),
-            ));
+    }
+}
+
+impl Sort {
+    fn as_str(self) -> &'static str {
+        match self {
+            Sort::Relevance => "relevance",
+            Sort::Words => "words",
+            Sort::Created => "created",
+            Sort::Modified => "modified",
+            Sort::Random => "random",
         }
-    };
-
-    let url = "https://tatoeba.org/en/api_v0/search";
-    let upstream = match state.http_client().get(url).query(&params).send().await {
-        Ok(r) => r,
-        Err(e) => {
-            error!(error = %e, %url, %query, "network error talking to Tatoeba");
-            return Err((
-                StatusCode::BAD_GATEWAY,
-                "Tatoeba upstream error".into(),
-            ));
+    }
+}
+
+impl TransFilter {
+    fn as_str(self) -> &'static str {
+        match self {
+            TransFilter::Limit => "limit",
+            TransFilter::Exclude => "exclude",
         }
-    };
-
-    let status = upstream.status();
-    let headers = upstream.headers().clone();
-
-    let body_bytes = match upstream.bytes().await {
-        Ok(b) => b,
-        Err(e) => {
-            error!(error = %e, "failed to read Tatoeba response body");
-            return Err((
-                StatusCode::BAD_GATEWAY,
-                "Tatoeba upstream error".into(),
+    }
+}
+
+impl TransLink {
+    fn as_str(self) -> &'static str {
+        match self {
+            TransLink::Direct => "direct",
+            TransLink::Indirect => "indirect",
+        }
+    }
+}
+
+impl TatoebaProxyQuery {
+    /// Checks the values and returns the parameters to send upstream, in a
+    /// fixed order so that the same search always has the same cache key.
+    pub fn validate(self) -> Result<Vec<(&'static str, String)>, ProxyError> {
+        let query = self.query.trim();
+        if query.is_empty() {
+            return Err(ProxyError::bad_request("query must not be empty"));
+        }
+        for (name, lang) in [
+            ("from", &self.from),
+            ("to", &self.to),
+            ("trans_to", &self.trans_to),
+        ] {
+            if let Some(lang) = lang
+                && !is_language_code(lang)
+            {
+                return Err(ProxyError::bad_request(format!(
+                    "unknown language `{lang}` in `{name}`"
+                )));
+            }
+        }
+        if self.page == Some(0) {
+            return Err(ProxyError::bad_request("page starts at 1"));
+        }
+        if let (Some(min), Some(max)) = (self.word_count_min, self.word_count_max)
+            && max < min
+        {
+            return Err(ProxyError::bad_request(
+                "word_count_min must not exceed word_count_max",
             ));
         }
-    };
 
-    let mut builder = Response::builder().status(status);
-    // Preserve content-type if present
-    if let Some(ct) = headers.get(header::CONTENT_TYPE) {
-        builder = builder.header(header::CONTENT_TYPE, ct);
+        let mut params = vec![("query", query.to_string())];
+        let mut push = |name, value: Option<String>| {
+            if let Some(value) = value {
+                params.push((name, value));
+            }
+        };
+        push("from", self.from);
+        push("to", self.to);
+        push("orphans", self.orphans.map(|v| v.as_str().into()));
+        push("unapproved", self.unapproved.map(|v| v.as_str().into()));
+        push("has_audio", self.has_audio.map(|v| v.as_str().into()));
+        push("user", self.user);
+        push("word_count_min", self.word_count_min.map(|v| v.to_string()));
+        push("word_count_max", self.word_count_max.map(|v| v.to_string()));
+        push("sort", self.sort.map(|v| v.as_str().into()));
+        push("sort_reverse", self.sort_reverse.map(|v| v.as_str().into()));
+        push("page", self.page.map(|v| v.to_string()));
+        push("trans_filter", self.trans_filter.map(|v| v.as_str().into()));
+        push("trans_to", self.trans_to);
+        push("trans_link", self.trans_link.map(|v| v.as_str().into()));
+        push(
+            "trans_has_audio",
+            self.trans_has_audio.map(|v| v.as_str().into()),
+        );
+        push(
+            "trans_unapproved",
+            self.trans_unapproved.map(|v| v.as_str().into()),
+        );
+        push("trans_orphan", self.trans_orphan.map(|v| v.as_str().into()));
+        push("trans_user", self.trans_user);
+        Ok(params)
+    }
+}
+
+/// Tatoeba codes its languages like ISO 639-3, plus codes of its own or
+/// from other standards (e.g. "ber" for Berber), so only the form is checked.
+fn is_language_code(lang: &str) -> bool {
+    lang.len() == 3 && lang.bytes().all(|b| b.is_ascii_lowercase())
+}
+
+/// Proxies `api_v0/search` with the parameters of [`TatoebaProxyQuery`].
+pub async fn tatoeba_proxy(
+    State(state): State<AppState>,
+    params: Result<Query<TatoebaProxyQuery>, QueryRejection>,
+) -> Result<Response<Body>, ProxyError> {
+    let Query(params) = params.map_err(|e| ProxyError::bad_request(e.body_text()))?;
+    let params = params.validate()?;
+    let url =
+        Url::parse_with_params(URL, &params).map_err(|e| ProxyError::bad_request(e.to_string()))?;
+
+    let upstream = state
+        .tatoeba_upstream()
+        .get(state.http_client(), url)
+        .await
+        .map_err(|e| ProxyError::from_source(UPSTREAM.error_code, e))?;
+    Ok(upstream.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{StatusCode, Uri};
+
+    fn parse(query: &str) -> Result<Vec<(&'static str, String)>, ProxyError> {
+        let uri: Uri = format!("/tatoeba?{query}").parse().unwrap();
+        let Query(params) = Query::<TatoebaProxyQuery>::try_from_uri(&uri)
+            .map_err(|e| ProxyError::bad_request(e.body_text()))?;
+        params.validate()
     }
 
-    let resp = builder
-        .body(Body::from(body_bytes))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    #[test]
+    fn known_parameters_are_forwarded_in_order() {
+        let params =
+            parse("sort=words&from=deu&query=%20Hund%20&to=eng&page=2&orphans=no").unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("query", "Hund".to_string()),
+                ("from", "deu".to_string()),
+                ("to", "eng".to_string()),
+                ("orphans", "no".to_string()),
+                ("sort", "words".to_string()),
+                ("page", "2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn languages_only_tatoeba_has_are_accepted() {
+        for lang in ["kab", "ber", "tok", "jbo", "lfn", "tlh"] {
+            let params = parse(&format!("query=Hund&from={lang}&trans_to={lang}")).expect(lang);
+            assert_eq!(params[1], ("from", lang.to_string()));
+        }
+    }
 
-    Ok(resp)
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        for query in [
+            "query=Hund&callback=x",
+            "from=deu",
+            "query=%20",
+            "query=Hund&from=de",
+            "query=Hund&to=DEU",
+            "query=Hund&trans_to=english",
+            "query=Hund&sort=best",
+            "query=Hund&orphans=maybe",
+            "query=Hund&page=0",
+            "query=Hund&page=-1",
+            "query=Hund&word_count_min=5&word_count_max=2",
+        ] {
+            let err = parse(query).expect_err(query);
+            assert_eq!(err.status, StatusCode::BAD_REQUEST, "{query}");
+            assert_eq!(err.code, "BAD_USER_INPUT", "{query}");
+        }
+        assert!(
+            parse("query=Hund&callback=x")
+                .unwrap_err()
+                .message
+                .contains("unknown field `callback`")
+        );
+    }
 }
@@ -0,0 +1,397 @@
+use crate::model::{Sentence, TranslationsSet, lexical_item_detail::Example};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tracing::info;
+
+/// All sentences in the Tatoeba exports are published under this license.
+const LICENSE: &str = "CC BY 2.0 FR";
+
+/// Rows are inserted in transactions of this size while importing.
+const IMPORT_BATCH: usize = 10_000;
+
+/// Local copy of the Tatoeba sentence and link exports, with a full-text
+/// index over the sentences.
+#[derive(Clone)]
+pub struct TatoebaStore {
+    pool: SqlitePool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub sentences: u64,
+    pub links: u64,
+    /// Malformed lines and sentences of languages which were not requested
+    pub skipped: u64,
+}
+
+impl TatoebaStore {
+    pub async fn new(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        for ddl in [
+            r#"
+            CREATE TABLE IF NOT EXISTS tatoeba_sentences (
+                id    INTEGER PRIMARY KEY,
+                lang  TEXT NOT NULL,
+                text  TEXT NOT NULL,
+                owner TEXT
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS tatoeba_links (
+                sentence_id    INTEGER NOT NULL,
+                translation_id INTEGER NOT NULL,
+                PRIMARY KEY (sentence_id, translation_id)
+            ) WITHOUT ROWID
+            "#,
+            // `lang` is indexed too so that a match can be limited to one
+            // language without scanning the hits of all the others.
+            r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS tatoeba_sentences_fts USING fts5 (
+                text, lang,
+                content = 'tatoeba_sentences', content_rowid = 'id'
+            )
+            "#,
+        ] {
+            sqlx::query(ddl).execute(&pool).await?;
+        }
+        Ok(Self { pool })
+    }
+
+    /// Replaces the stored corpus with the `sentences` export (either
+    /// `sentences.csv` or `sentences_detailed.csv`) and the `links` export.
+    /// Only sentences in `langs` are kept, all of them if it is empty.
+    ///
+    /// The exports are loaded into staging tables first, so that a failed
+    /// import leaves the previous corpus in place.
+    pub async fn import(
+        &self,
+        mut sentences: impl AsyncBufRead + Unpin,
+        mut links: impl AsyncBufRead + Unpin,
+        langs: &[String],
+    ) -> Result<ImportStats, sqlx::Error> {
+        let mut stats = ImportStats::default();
+        for ddl in [
+            "DROP TABLE IF EXISTS tatoeba_links_staging",
+            "DROP TABLE IF EXISTS tatoeba_sentences_staging",
+            r#"
+            CREATE TABLE tatoeba_sentences_staging (
+                id    INTEGER PRIMARY KEY,
+                lang  TEXT NOT NULL,
+                text  TEXT NOT NULL,
+                owner TEXT
+            )
+            "#,
+            r#"
+            CREATE TABLE tatoeba_links_staging (
+                sentence_id    INTEGER NOT NULL,
+                translation_id INTEGER NOT NULL,
+                PRIMARY KEY (sentence_id, translation_id)
+            ) WITHOUT ROWID
+            "#,
+        ] {
+            sqlx::query(ddl).execute(&self.pool).await?;
+        }
+        let mut tx = self.pool.begin().await?;
+
+        let mut line = String::new();
+        let mut in_batch = 0;
+        loop {
+            line.clear();
+            if sentences.read_line(&mut line).await? == 0 {
+                break;
+            }
+            // id, lang, text[, username, added, modified]
+            let cols: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+            let (Some(id), Some(&lang), Some(&text)) = (
+                cols.first().and_then(|id| id.parse::<i64>().ok()),
+                cols.get(1),
+                cols.get(2),
+            ) else {
+                stats.skipped += 1;
+                continue;
+            };
+            if (!langs.is_empty() && !langs.iter().any(|l| l == lang)) || text.is_empty() {
+                stats.skipped += 1;
+                continue;
+            }
+            let owner = cols.get(3).filter(|o| !o.is_empty() && **o != "\\N");
+
+            sqlx::query("INSERT OR REPLACE INTO tatoeba_sentences_staging (id, lang, text, owner) VALUES (?1, ?2, ?3, ?4)")
+                .bind(id)
+                .bind(lang)
+                .bind(text)
+                .bind(owner)
+                .execute(&mut *tx)
+                .await?;
+            stats.sentences += 1;
+            in_batch += 1;
+            if in_batch == IMPORT_BATCH {
+                tx.commit().await?;
+                tx = self.pool.begin().await?;
+                in_batch = 0;
+                info!(sentences = stats.sentences, "importing Tatoeba sentences");
+            }
+        }
+
+        loop {
+            line.clear();
+            if links.read_line(&mut line).await? == 0 {
+                break;
+            }
+            let mut cols = line.trim_end_matches(['\r', '\n']).split('\t');
+            let (Some(Ok(sentence_id)), Some(Ok(translation_id))) = (
+                cols.next().map(str::parse::<i64>),
+                cols.next().map(str::parse::<i64>),
+            ) else {
+                stats.skipped += 1;
+                continue;
+            };
+            // Links to sentences which were not imported are dropped.
+            let inserted = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO tatoeba_links_staging (sentence_id, translation_id)
+                SELECT ?1, ?2
+                WHERE EXISTS (SELECT 1 FROM tatoeba_sentences_staging WHERE id = ?1)
+                  AND EXISTS (SELECT 1 FROM tatoeba_sentences_staging WHERE id = ?2)
+                "#,
+            )
+            .bind(sentence_id)
+            .bind(translation_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            stats.links += inserted;
+            in_batch += 1;
+            if in_batch == IMPORT_BATCH {
+                tx.commit().await?;
+                tx = self.pool.begin().await?;
+                in_batch = 0;
+            }
+        }
+
+        tx.commit().await?;
+
+        info!("replacing Tatoeba corpus");
+        let mut tx = self.pool.begin().await?;
+        for sql in [
+            "DELETE FROM tatoeba_links",
+            "DELETE FROM tatoeba_sentences",
+            "INSERT INTO tatoeba_sentences SELECT id, lang, text, owner FROM tatoeba_sentences_staging",
+            "INSERT INTO tatoeba_links SELECT sentence_id, translation_id FROM tatoeba_links_staging",
+            "INSERT INTO tatoeba_sentences_fts (tatoeba_sentences_fts) VALUES ('rebuild')",
+            "DROP TABLE tatoeba_links_staging",
+            "DROP TABLE tatoeba_sentences_staging",
+        ] {
+            sqlx::query(sql).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(stats)
+    }
+
+    /// Up to `limit` sentences in `lang_from_iso3` containing the word(s) of
+    /// `query`, best matches first, each with its direct translations into
+    /// `lang_to_iso3`.
+    pub async fn search(
+        &self,
+        query: &str,
+        lang_from_iso3: &str,
+        lang_to_iso3: &str,
+        limit: usize,
+    ) -> Result<Vec<Example>, sqlx::Error> {
+        let source = "tatoeba";
+        let matching: Vec<(i64, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT s.id, s.text, s.owner
+            FROM tatoeba_sentences_fts
+            JOIN tatoeba_sentences s ON s.id = tatoeba_sentences_fts.rowid
+            WHERE tatoeba_sentences_fts MATCH ?1
+            ORDER BY rank
+            LIMIT ?2
+            "#,
+        )
+        .bind(match_expression(query, lang_from_iso3))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        if matching.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids = matching
+            .iter()
+            .map(|(id, _, _)| id.to_string())
+            .collect::<Vec<_>>();
+        let translations: Vec<(i64, i64, String, Option<String>)> = sqlx::query_as(&format!(
+            r#"
+            SELECT l.sentence_id, t.id, t.text, t.owner
+            FROM tatoeba_links l
+            JOIN tatoeba_sentences t ON t.id = l.translation_id
+            WHERE l.sentence_id IN ({}) AND t.lang = ?1
+            ORDER BY t.id
+            "#,
+            ids.join(", ")
+        ))
+        .bind(lang_to_iso3)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut translations_of = HashMap::<i64, Vec<Sentence>>::new();
+        for (sentence_id, id, text, owner) in translations {
+            translations_of
+                .entry(sentence_id)
+                .or_default()
+                .push(sentence(id, text, owner, lang_to_iso3, source));
+        }
+
+        Ok(matching
+            .into_iter()
+            .map(|(id, text, owner)| Example {
+                translations_set: TranslationsSet {
+                    original: sentence(id, text, owner, lang_from_iso3, source),
+                    translations: translations_of.remove(&id).unwrap_or_default(),
+                    translations_qualities: None,
+                },
+                source: source.to_string(),
+            })
+            .collect())
+    }
+}
+
+fn sentence(
+    id: i64,
+    text: String,
+    owner: Option<String>,
+    lang_iso3: &str,
+    source: &str,
+) -> Sentence {
+    Sentence {
+        source_id: Some(id.to_string()),
+        owner,
+        license: Some(LICENSE.to_string()),
+        ..Sentence::new(text, lang_iso3, source)
+    }
+}
+
+/// FTS5 query matching sentences in `lang_iso3` containing `query` as a
+/// phrase. Everything is quoted, so user input cannot inject FTS syntax.
+fn match_expression(query: &str, lang_iso3: &str) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+    format!(
+        "lang : {} AND text : {}",
+        quote(lang_iso3),
+        quote(query.trim())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const SENTENCES: &str = "1\tdeu\tDer Hund bellt.\thans\t2010-01-01\t\\N
+2\teng\tThe dog barks.\t\\N\t2010-01-01\t\\N
+3\tfra\tLe chien aboie.\tmarie\t2010-01-01\t\\N
+4\tdeu\tDie Katze schläft.\thans\t2010-01-01\t\\N
+5\teng\tHund is German for dog.\tjohn\t2010-01-01\t\\N
+6\tdeu\tHunde, die bellen, beißen nicht.\thans\t2010-01-01\t\\N
+not a sentence
+";
+
+    const LINKS: &str = "1\t2\n1\t3\n2\t1\n3\t1\n6\t99\n";
+
+    async fn imported_store(langs: &[&str]) -> (TatoebaStore, ImportStats) {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("connect :memory:");
+        let store = TatoebaStore::new(pool).await.expect("store");
+        let langs: Vec<String> = langs.iter().map(|l| l.to_string()).collect();
+        let stats = store
+            .import(SENTENCES.as_bytes(), LINKS.as_bytes(), &langs)
+            .await
+            .expect("import");
+        (store, stats)
+    }
+
+    #[tokio::test]
+    async fn finds_word_in_language_with_translations() {
+        let (store, stats) = imported_store(&["deu", "eng"]).await;
+        assert_eq!(
+            stats,
+            ImportStats {
+                sentences: 5,
+                links: 2,
+                skipped: 2,
+            }
+        );
+
+        let examples = store.search("hund", "deu", "eng", 10).await.unwrap();
+        let source = "tatoeba".to_string();
+        assert_eq!(
+            examples,
+            vec![Example {
+                translations_set: TranslationsSet {
+                    original: Sentence {
+                        source_id: Some("1".into()),
+                        owner: Some("hans".into()),
+                        license: Some(LICENSE.into()),
+                        ..Sentence::new("Der Hund bellt.", "deu", &source)
+                    },
+                    translations: vec![Sentence {
+                        source_id: Some("2".into()),
+                        license: Some(LICENSE.into()),
+                        ..Sentence::new("The dog barks.", "eng", &source)
+                    }],
+                    translations_qualities: None,
+                },
+                source: source.clone(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn query_syntax_is_not_interpreted() {
+        let (store, _) = imported_store(&[]).await;
+        assert!(
+            store
+                .search("\"Hund OR", "deu", "eng", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            store
+                .search("nichts", "deu", "eng", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            store
+                .search("bellen", "deu", "eng", 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_import_keeps_previous_corpus() {
+        let (store, _) = imported_store(&["deu", "eng"]).await;
+
+        // Invalid UTF-8 fails the read after the first sentence was staged.
+        let broken: &[u8] = b"7\tdeu\tDer Hund schl\xc3\xa4ft.\n\xff\xfe\n";
+        let langs = vec!["deu".to_string()];
+        assert!(
+            store
+                .import(broken, LINKS.as_bytes(), &langs)
+                .await
+                .is_err()
+        );
+
+        let examples = store.search("hund", "deu", "eng", 10).await.unwrap();
+        assert_eq!(examples.len(), 1);
+        assert_eq!(examples[0].translations_set.translations.len(), 1);
+    }
+}
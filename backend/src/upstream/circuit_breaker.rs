@@ -0,0 +1,279 @@
+use super::source_error::SourceError;
+use axum::http::StatusCode;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Status of requests refused because the upstream's circuit is open.
+pub const UNAVAILABLE_STATUS: StatusCode = StatusCode::SERVICE_UNAVAILABLE;
+/// Error code of requests refused because the upstream's circuit is open.
+pub const UNAVAILABLE_CODE: &str = "UPSTREAM_UNAVAILABLE";
+
+/// Consecutive failures which open the circuit.
+const FAILURE_THRESHOLD: u32 = 5;
+/// How long an open circuit refuses requests before letting a probe through.
+const OPEN_FOR: Duration = Duration::from_secs(30);
+
+/// Stops sending requests to an upstream which keeps failing, so that
+/// callers fail fast instead of waiting for timeouts.
+///
+/// After `failure_threshold` consecutive failures the circuit opens and
+/// requests are refused for `open_for`. Then it half-opens: one request is
+/// let through as a probe, and closes the circuit if it succeeds or opens it
+/// again if it fails.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    open_for: Duration,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    state: State,
+    consecutive_failures: u32,
+    last_failure: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Closed,
+    Open {
+        until: Instant,
+    },
+    /// A probe abandoned for longer than `open_for` is replaced by a new one.
+    HalfOpen {
+        probe_started: Instant,
+    },
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakerStatus {
+    pub upstream: &'static str,
+    /// "closed", "open" or "halfOpen"
+    pub state: &'static str,
+    pub consecutive_failures: u32,
+    /// Until the next probe, when open
+    pub retry_in_secs: Option<u64>,
+    pub last_failure: Option<String>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            name,
+            failure_threshold,
+            open_for,
+            inner: Arc::new(Mutex::new(Inner {
+                state: State::Closed,
+                consecutive_failures: 0,
+                last_failure: None,
+            })),
+        }
+    }
+
+    /// Whether a request may be sent now; [`SourceError::Unavailable`] if
+    /// not. The outcome of an allowed request must be recorded with
+    /// [`CircuitBreaker::record`].
+    pub fn try_acquire(&self) -> Result<(), SourceError> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        match inner.state {
+            State::Closed => return Ok(()),
+            State::Open { until } if until <= now => {}
+            State::HalfOpen { probe_started } if self.open_for <= probe_started.elapsed() => {}
+            State::Open { until } => {
+                return Err(self.unavailable(Some(until - now)));
+            }
+            State::HalfOpen { .. } => return Err(self.unavailable(None)),
+        }
+        info!(upstream = self.name, "circuit half-open, probing");
+        inner.state = State::HalfOpen { probe_started: now };
+        Ok(())
+    }
+
+    /// Records the outcome of a request allowed by `try_acquire`. Only
+    /// failures of the upstream itself count, see
+    /// [`SourceError::is_upstream_failure`].
+    pub fn record<T>(&self, result: &Result<T, SourceError>) {
+        let mut inner = self.inner.lock().unwrap();
+        match result {
+            Err(e) if e.is_upstream_failure() => {
+                inner.consecutive_failures += 1;
+                inner.last_failure = Some(e.message().to_string());
+                let probe_failed = matches!(inner.state, State::HalfOpen { .. });
+                if probe_failed || self.failure_threshold <= inner.consecutive_failures {
+                    if !matches!(inner.state, State::Open { .. }) {
+                        warn!(
+                            upstream = self.name,
+                            failures = inner.consecutive_failures,
+                            "circuit opened"
+                        );
+                    }
+                    inner.state = State::Open {
+                        until: Instant::now() + self.open_for,
+                    };
+                }
+            }
+            _ => {
+                if !matches!(inner.state, State::Closed) {
+                    info!(upstream = self.name, "circuit closed");
+                }
+                inner.state = State::Closed;
+                inner.consecutive_failures = 0;
+            }
+        }
+    }
+
+    /// Runs `request` unless the circuit is open, and records its outcome.
+    pub async fn call<T>(
+        &self,
+        request: impl Future<Output = Result<T, SourceError>>,
+    ) -> Result<T, SourceError> {
+        self.try_acquire()?;
+        let result = request.await;
+        self.record(&result);
+        result
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let inner = self.inner.lock().unwrap();
+        let (state, retry_in) = match inner.state {
+            State::Closed => ("closed", None),
+            State::Open { until } => (
+                "open",
+                Some(until.saturating_duration_since(Instant::now())),
+            ),
+            State::HalfOpen { .. } => ("halfOpen", None),
+        };
+        BreakerStatus {
+            upstream: self.name,
+            state,
+            consecutive_failures: inner.consecutive_failures,
+            retry_in_secs: retry_in.map(|d| d.as_secs()),
+            last_failure: inner.last_failure.clone(),
+        }
+    }
+
+    fn unavailable(&self, retry_in: Option<Duration>) -> SourceError {
+        let message = match retry_in {
+            Some(retry_in) => format!(
+                "{} is unavailable, retrying in {}s",
+                self.name,
+                retry_in.as_secs() + 1
+            ),
+            None => format!("{} is unavailable, probing", self.name),
+        };
+        SourceError::Unavailable(message)
+    }
+}
+
+/// One breaker per upstream, shared by the GraphQL sources and the proxies.
+#[derive(Clone)]
+pub struct UpstreamBreakers {
+    pub kaikki: CircuitBreaker,
+    pub tatoeba: CircuitBreaker,
+    pub leipzig: CircuitBreaker,
+    /// The configured LLM API, OpenAI or compatible
+    pub openai: CircuitBreaker,
+}
+
+impl Default for UpstreamBreakers {
+    fn default() -> Self {
+        let breaker = |name| CircuitBreaker::new(name, FAILURE_THRESHOLD, OPEN_FOR);
+        Self {
+            kaikki: breaker("kaikki"),
+            tatoeba: breaker("tatoeba"),
+            leipzig: breaker("leipzig"),
+            openai: breaker("openai"),
+        }
+    }
+}
+
+impl UpstreamBreakers {
+    pub fn all(&self) -> [&CircuitBreaker; 4] {
+        [&self.kaikki, &self.tatoeba, &self.leipzig, &self.openai]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure() -> Result<(), SourceError> {
+        Err(SourceError::Upstream(
+            StatusCode::BAD_GATEWAY,
+            "down".into(),
+        ))
+    }
+
+    #[test]
+    fn opens_after_threshold_and_half_opens_for_one_probe() {
+        let breaker = CircuitBreaker::new("test", 2, Duration::ZERO);
+        breaker.record(&failure());
+        assert_eq!(breaker.status().state, "closed");
+        breaker.record(&Err::<(), _>(SourceError::Upstream(
+            StatusCode::NOT_FOUND,
+            "missing".into(),
+        )));
+        assert_eq!(breaker.status().consecutive_failures, 0);
+
+        breaker.record(&failure());
+        breaker.record(&failure());
+        assert_eq!(breaker.status().state, "open");
+
+        // `open_for` is over at once: the first caller probes, others wait.
+        assert!(breaker.try_acquire().is_ok());
+        assert_eq!(breaker.status().state, "halfOpen");
+        let refused = CircuitBreaker {
+            open_for: OPEN_FOR,
+            ..breaker.clone()
+        }
+        .try_acquire()
+        .expect_err("Err");
+        assert_eq!(refused.status(), UNAVAILABLE_STATUS);
+
+        breaker.record(&Ok(()));
+        assert_eq!(
+            breaker.status(),
+            BreakerStatus {
+                upstream: "test",
+                state: "closed",
+                consecutive_failures: 0,
+                retry_in_secs: None,
+                last_failure: Some("down".into()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn open_circuit_fails_fast() {
+        let breaker = CircuitBreaker::new("test", 1, OPEN_FOR);
+        assert!(breaker.call(async { failure() }).await.is_err());
+
+        let err = breaker
+            .call(async { Ok::<_, SourceError>("not sent") })
+            .await
+            .expect_err("Err");
+        assert_eq!(err.status(), UNAVAILABLE_STATUS);
+        assert!(err.message().starts_with("test is unavailable"), "{err:?}");
+        assert_eq!(breaker.status().retry_in_secs, Some(29));
+    }
+
+    #[tokio::test]
+    async fn upstream_client_errors_do_not_open_the_circuit() {
+        let breaker = CircuitBreaker::new("test", 1, OPEN_FOR);
+        for status in [StatusCode::UNAUTHORIZED, StatusCode::TOO_MANY_REQUESTS] {
+            let err = breaker
+                .call(async { Err::<(), _>(SourceError::Upstream(status, "rejected".into())) })
+                .await
+                .expect_err("Err");
+            assert_eq!(err, SourceError::Upstream(status, "rejected".into()));
+        }
+        assert_eq!(breaker.status().state, "closed");
+        assert_eq!(breaker.status().consecutive_failures, 0);
+    }
+}
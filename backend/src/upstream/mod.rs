@@ -0,0 +1,6 @@
+pub(crate) mod circuit_breaker;
+pub(crate) mod proxy_error;
+pub(crate) mod response_cache;
+pub(crate) mod source_error;
+pub(crate) mod upstream_proxy;
+pub mod upstream_status;
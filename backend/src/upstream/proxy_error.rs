@@ -0,0 +1,49 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use super::source_error::SourceError;
+
+/// Error of a proxy endpoint, answered as
+/// `{"code": ..., "httpStatus": ..., "message": ...}` like the extensions of
+/// GraphQL errors.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProxyError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ProxyError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "BAD_USER_INPUT", message)
+    }
+
+    /// Maps the errors of the clients, reporting failures of the upstream
+    /// under `upstream_code`, see [`SourceError::code`].
+    pub fn from_source(upstream_code: &'static str, e: SourceError) -> Self {
+        Self::new(e.status(), e.code(upstream_code), e.message())
+    }
+}
+
+impl IntoResponse for ProxyError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "code": self.code,
+            "httpStatus": self.status.as_u16(),
+            "message": self.message,
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
@@ -0,0 +1,177 @@
+use super::upstream_proxy::UpstreamResponse;
+use axum::http::{HeaderMap, HeaderValue, header};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A cached upstream response with what is needed to revalidate it.
+#[derive(Clone)]
+pub struct CachedResponse {
+    pub response: UpstreamResponse,
+    pub etag: Option<HeaderValue>,
+    pub last_modified: Option<HeaderValue>,
+    stored_at: Instant,
+    max_age: Duration,
+}
+
+impl CachedResponse {
+    /// `None` if the upstream forbids storing the response.
+    pub fn new(
+        response: UpstreamResponse,
+        headers: &HeaderMap,
+        default_max_age: Duration,
+    ) -> Option<Self> {
+        Some(Self {
+            response,
+            etag: headers.get(header::ETAG).cloned(),
+            last_modified: headers.get(header::LAST_MODIFIED).cloned(),
+            stored_at: Instant::now(),
+            max_age: max_age(headers, default_max_age)?,
+        })
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.stored_at.elapsed() < self.max_age
+    }
+}
+
+/// How long a response may be served without revalidation, from its
+/// `Cache-Control` header; `None` for `no-store` and `private`.
+fn max_age(headers: &HeaderMap, default_max_age: Duration) -> Option<Duration> {
+    let Some(cache_control) = headers
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return Some(default_max_age);
+    };
+    let mut max_age = default_max_age;
+    for directive in cache_control
+        .split(',')
+        .map(|d| d.trim().to_ascii_lowercase())
+    {
+        match directive.split_once('=') {
+            _ if directive == "no-store" || directive == "private" => return None,
+            _ if directive == "no-cache" => max_age = Duration::ZERO,
+            Some(("max-age", secs)) => {
+                if let Ok(secs) = secs.trim_matches('"').parse() {
+                    max_age = Duration::from_secs(secs);
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(max_age)
+}
+
+/// Responses by URL, limited to `max_bytes` of bodies; the oldest responses
+/// are evicted first.
+pub struct ResponseCache {
+    entries: HashMap<String, CachedResponse>,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl ResponseCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            bytes: 0,
+            max_bytes,
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        self.entries.get(url).cloned()
+    }
+
+    pub fn insert(&mut self, url: String, entry: CachedResponse) {
+        self.remove(&url);
+        let size = entry.response.body.len();
+        if size > self.max_bytes {
+            return;
+        }
+        while self.bytes + size > self.max_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.stored_at)
+                .map(|(url, _)| url.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+        self.bytes += size;
+        self.entries.insert(url, entry);
+    }
+
+    /// Marks the response as fresh again after the upstream confirmed it is
+    /// unchanged; a 304 may also update `Cache-Control`.
+    pub fn revalidated(&mut self, url: &str, headers: &HeaderMap, default_max_age: Duration) {
+        let Some(entry) = self.entries.get_mut(url) else {
+            return;
+        };
+        match max_age(headers, default_max_age) {
+            Some(max_age) => {
+                entry.stored_at = Instant::now();
+                entry.max_age = max_age;
+            }
+            None => self.remove(url),
+        }
+    }
+
+    fn remove(&mut self, url: &str) {
+        if let Some(old) = self.entries.remove(url) {
+            self.bytes -= old.response.body.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Bytes;
+    use axum::http::StatusCode;
+
+    fn entry(body: &'static str, headers: &HeaderMap) -> CachedResponse {
+        let response = UpstreamResponse {
+            status: StatusCode::OK,
+            content_type: None,
+            body: Bytes::from_static(body.as_bytes()),
+        };
+        CachedResponse::new(response, headers, Duration::from_secs(60)).expect("cacheable")
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_beyond_max_bytes() {
+        let mut cache = ResponseCache::new(10);
+        cache.insert("a".into(), entry("1234", &HeaderMap::new()));
+        cache.insert("b".into(), entry("1234", &HeaderMap::new()));
+        cache.insert("c".into(), entry("1234", &HeaderMap::new()));
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+        assert_eq!(cache.bytes, 8);
+
+        cache.insert("huge".into(), entry("12345678901", &HeaderMap::new()));
+        assert!(cache.get("huge").is_none());
+        assert_eq!(cache.bytes, 8);
+    }
+
+    #[test]
+    fn cache_control_is_honoured() {
+        let mut headers = HeaderMap::new();
+        assert!(entry("x", &headers).is_fresh());
+
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("public, max-age=0"),
+        );
+        assert!(!entry("x", &headers).is_fresh());
+
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        assert!(!entry("x", &headers).is_fresh());
+
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        let response = entry("x", &HeaderMap::new()).response;
+        assert!(CachedResponse::new(response, &headers, Duration::from_secs(60)).is_none());
+    }
+}
@@ -0,0 +1,89 @@
+use axum::http::StatusCode;
+
+use super::circuit_breaker::{UNAVAILABLE_CODE, UNAVAILABLE_STATUS};
+
+/// Error of the clients of the upstream sources, typed by who caused it.
+/// Statuses and error codes are mapped from the variant, never from the
+/// status an upstream answered: its 400 is not our client's fault, and its
+/// 402 is not our LLM budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceError {
+    /// Refused before asking the upstream, e.g. an unknown language.
+    BadInput(String),
+    /// The circuit of the upstream is open.
+    Unavailable(String),
+    /// The daily LLM budget is spent.
+    BudgetExceeded(String),
+    /// The upstream answered `status`, or could not be reached or understood
+    /// (`BAD_GATEWAY`, `GATEWAY_TIMEOUT`).
+    Upstream(StatusCode, String),
+    /// Our own failure, e.g. of a local DB.
+    Internal(String),
+}
+
+/// Status of the budget errors.
+pub const BUDGET_EXCEEDED_STATUS: StatusCode = StatusCode::PAYMENT_REQUIRED;
+
+impl SourceError {
+    /// Status reported to our clients: whatever the upstream answered, its
+    /// failures are gateway errors.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            SourceError::BadInput(_) => StatusCode::BAD_REQUEST,
+            SourceError::Unavailable(_) => UNAVAILABLE_STATUS,
+            SourceError::BudgetExceeded(_) => BUDGET_EXCEEDED_STATUS,
+            SourceError::Upstream(StatusCode::GATEWAY_TIMEOUT, _) => StatusCode::GATEWAY_TIMEOUT,
+            SourceError::Upstream(..) => StatusCode::BAD_GATEWAY,
+            SourceError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Error code reported to our clients; `upstream_code`, e.g.
+    /// "UPSTREAM_KAIKKI", for failures of the upstream or our own.
+    pub fn code(&self, upstream_code: &'static str) -> &'static str {
+        match self {
+            SourceError::BadInput(_) => "BAD_USER_INPUT",
+            SourceError::Unavailable(_) => UNAVAILABLE_CODE,
+            SourceError::BudgetExceeded(_) => "BUDGET_EXCEEDED",
+            SourceError::Upstream(..) | SourceError::Internal(_) => upstream_code,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            SourceError::BadInput(message)
+            | SourceError::Unavailable(message)
+            | SourceError::BudgetExceeded(message)
+            | SourceError::Upstream(_, message)
+            | SourceError::Internal(message) => message,
+        }
+    }
+
+    /// Whether the upstream is down: unreachable, timing out or answering
+    /// 5xx. E.g. a 404 or a rejected API key shows that it is up.
+    pub fn is_upstream_failure(&self) -> bool {
+        matches!(self, SourceError::Upstream(status, _) if status.is_server_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upstream_client_errors_are_bad_gateways() {
+        for status in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::UNAUTHORIZED,
+            StatusCode::PAYMENT_REQUIRED,
+        ] {
+            let err = SourceError::Upstream(status, "rejected".into());
+            assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+            assert_eq!(err.code("UPSTREAM_LLM"), "UPSTREAM_LLM");
+            assert!(!err.is_upstream_failure());
+        }
+        let err = SourceError::BadInput("unsupported lang".into());
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.code("UPSTREAM_KAIKKI"), "BAD_USER_INPUT");
+    }
+}
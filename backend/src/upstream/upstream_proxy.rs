@@ -0,0 +1,388 @@
+use super::circuit_breaker::CircuitBreaker;
+use super::response_cache::{CachedResponse, ResponseCache};
+use super::source_error::SourceError;
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use reqwest::{Client, Url};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+
+/// How an upstream is talked to.
+#[derive(Clone, Copy, Debug)]
+pub struct UpstreamConfig {
+    /// For logs and error messages, e.g. "Kaikki"
+    pub name: &'static str,
+    /// Error code of failed requests, e.g. "UPSTREAM_KAIKKI"
+    pub error_code: &'static str,
+    /// Per attempt, including reading the body
+    pub timeout: Duration,
+    /// Attempts after the first one on 5xx and timeouts
+    pub retries: u32,
+    /// The n-th retry waits between half and all of `backoff * 2^(n-1)`
+    pub backoff: Duration,
+    /// Freshness of responses without `Cache-Control: max-age`
+    pub default_max_age: Duration,
+    /// Total size of the cached bodies
+    pub cache_bytes: usize,
+}
+
+/// Response of an upstream as passed on to our clients.
+#[derive(Clone, Debug)]
+pub struct UpstreamResponse {
+    pub status: StatusCode,
+    pub content_type: Option<HeaderValue>,
+    pub body: Bytes,
+}
+
+impl IntoResponse for UpstreamResponse {
+    fn into_response(self) -> Response {
+        let mut res = Response::new(Body::from(self.body));
+        *res.status_mut() = self.status;
+        if let Some(content_type) = self.content_type {
+            res.headers_mut().insert(header::CONTENT_TYPE, content_type);
+        }
+        res
+    }
+}
+
+/// Sends GET requests to one upstream with its timeout, retries, cache and
+/// circuit breaker. Successful responses are cached by URL and revalidated
+/// with `If-None-Match`/`If-Modified-Since` once stale.
+#[derive(Clone)]
+pub struct Upstream {
+    config: UpstreamConfig,
+    cache: Arc<Mutex<ResponseCache>>,
+    breaker: CircuitBreaker,
+}
+
+/// What came back from one attempt.
+struct RawResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Upstream {
+    pub fn new(config: UpstreamConfig, breaker: CircuitBreaker) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(ResponseCache::new(config.cache_bytes))),
+            config,
+            breaker,
+        }
+    }
+
+    /// Non-5xx responses are passed on as they are, so that e.g. a 404 of the
+    /// upstream stays a 404. A stale cached response is served if the
+    /// upstream cannot be reached, or its circuit is open, to revalidate it.
+    pub async fn get(
+        &self,
+        http_client: &Client,
+        url: Url,
+    ) -> Result<UpstreamResponse, SourceError> {
+        let key = url.as_str().to_string();
+        let cached = self.cache.lock().unwrap().get(&key);
+        if let Some(cached) = &cached
+            && cached.is_fresh()
+        {
+            return Ok(cached.response.clone());
+        }
+
+        let mut conditional = HeaderMap::new();
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                conditional.insert(header::IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                conditional.insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+
+        let sent = match self.breaker.try_acquire() {
+            Ok(()) => {
+                let sent = self.send(http_client, &url, conditional).await;
+                self.breaker.record(&sent);
+                sent
+            }
+            Err(e) => Err(e),
+        };
+        let raw = match sent {
+            Ok(raw) => raw,
+            Err(e) => {
+                return match cached {
+                    Some(cached) => {
+                        warn!(upstream = self.config.name, error = %e.message(), %url, "serving stale response");
+                        Ok(cached.response)
+                    }
+                    None => Err(e),
+                };
+            }
+        };
+
+        if raw.status == StatusCode::NOT_MODIFIED
+            && let Some(cached) = cached
+        {
+            self.cache
+                .lock()
+                .unwrap()
+                .revalidated(&key, &raw.headers, self.config.default_max_age);
+            return Ok(cached.response);
+        }
+
+        let response = UpstreamResponse {
+            status: raw.status,
+            content_type: raw.headers.get(header::CONTENT_TYPE).cloned(),
+            body: raw.body,
+        };
+        if response.status == StatusCode::OK
+            && let Some(entry) =
+                CachedResponse::new(response.clone(), &raw.headers, self.config.default_max_age)
+        {
+            self.cache.lock().unwrap().insert(key, entry);
+        }
+        Ok(response)
+    }
+
+    /// Retries 5xx responses, timeouts and connection failures.
+    async fn send(
+        &self,
+        http_client: &Client,
+        url: &Url,
+        headers: HeaderMap,
+    ) -> Result<RawResponse, SourceError> {
+        let name = self.config.name;
+        let mut attempt = 0;
+        loop {
+            let started = Instant::now();
+            let result = self.send_once(http_client, url, headers.clone()).await;
+            let retryable = match &result {
+                Ok(raw) => raw.status.is_server_error(),
+                Err(e) => e.is_timeout() || e.is_connect(),
+            };
+            if !retryable || attempt == self.config.retries {
+                return match result {
+                    Ok(raw) if raw.status.is_server_error() => {
+                        let body = String::from_utf8_lossy(&raw.body);
+                        error!(upstream = name, status = %raw.status, body = %crate::util::truncate(&body), "upstream non-success");
+                        Err(SourceError::Upstream(
+                            raw.status,
+                            format!("{name} returned {}", raw.status),
+                        ))
+                    }
+                    Ok(raw) => Ok(raw),
+                    Err(e) => {
+                        error!(upstream = name, error = %e, %url, "network error talking to upstream");
+                        let status = if e.is_timeout() {
+                            StatusCode::GATEWAY_TIMEOUT
+                        } else {
+                            StatusCode::BAD_GATEWAY
+                        };
+                        Err(SourceError::Upstream(
+                            status,
+                            format!("{name} upstream error"),
+                        ))
+                    }
+                };
+            }
+            attempt += 1;
+            let delay = jittered(self.config.backoff * 2u32.pow(attempt - 1));
+            warn!(
+                upstream = name,
+                attempt,
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                delay_ms = delay.as_millis() as u64,
+                "retrying upstream request"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn send_once(
+        &self,
+        http_client: &Client,
+        url: &Url,
+        headers: HeaderMap,
+    ) -> Result<RawResponse, reqwest::Error> {
+        let res = http_client
+            .get(url.clone())
+            .headers(headers)
+            .timeout(self.config.timeout)
+            .send()
+            .await?;
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = res.bytes().await?;
+        Ok(RawResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// A random duration between half of `max` and `max`, so that clients
+/// retrying at the same time spread out.
+fn jittered(max: Duration) -> Duration {
+    let random = RandomState::new().hash_one(Instant::now());
+    max / 2 + max.mul_f64((random % 1_000) as f64 / 2_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upstream::proxy_error::ProxyError;
+    use mockito::Server;
+
+    fn upstream() -> Upstream {
+        upstream_with(CircuitBreaker::new("test", 5, Duration::from_secs(30)))
+    }
+
+    fn upstream_with(breaker: CircuitBreaker) -> Upstream {
+        Upstream::new(
+            UpstreamConfig {
+                name: "Test",
+                error_code: "UPSTREAM_TEST",
+                timeout: Duration::from_secs(5),
+                retries: 2,
+                backoff: Duration::from_millis(1),
+                default_max_age: Duration::ZERO,
+                cache_bytes: 1_000,
+            },
+            breaker,
+        )
+    }
+
+    #[tokio::test]
+    async fn stale_responses_are_revalidated_with_etag() {
+        let mut server = Server::new_async().await;
+        let first = server
+            .mock("GET", "/page")
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_header("etag", "\"v1\"")
+            .with_header("content-type", "application/jsonl")
+            .with_body("{}\n")
+            .expect(1)
+            .create();
+        let revalidation = server
+            .mock("GET", "/page")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .expect(1)
+            .create();
+
+        let upstream = upstream();
+        let url = Url::parse(&format!("{}/page", server.url())).unwrap();
+        for _ in 0..2 {
+            let res = upstream.get(&Client::new(), url.clone()).await.expect("Ok");
+            assert_eq!(res.status, StatusCode::OK);
+            assert_eq!(res.body, Bytes::from_static(b"{}\n"));
+            assert_eq!(res.content_type.unwrap(), "application/jsonl");
+        }
+        first.assert();
+        revalidation.assert();
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let mut server = Server::new_async().await;
+        let failing = server
+            .mock("GET", "/flaky")
+            .with_status(503)
+            .expect(2)
+            .create();
+        let ok = server
+            .mock("GET", "/flaky")
+            .with_body("ok")
+            .expect(1)
+            .create();
+        let res = upstream()
+            .get(
+                &Client::new(),
+                Url::parse(&format!("{}/flaky", server.url())).unwrap(),
+            )
+            .await
+            .expect("Ok");
+        failing.assert();
+        ok.assert();
+        assert_eq!(res.body, Bytes::from_static(b"ok"));
+    }
+
+    #[tokio::test]
+    async fn persistent_errors_become_json_errors() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/down")
+            .with_status(500)
+            .with_body("oops")
+            .expect(3)
+            .create();
+        let err = upstream()
+            .get(
+                &Client::new(),
+                Url::parse(&format!("{}/down", server.url())).unwrap(),
+            )
+            .await
+            .expect_err("Err");
+        m.assert();
+        assert_eq!(
+            err,
+            SourceError::Upstream(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Test returned 500 Internal Server Error".into()
+            )
+        );
+
+        let res = ProxyError::from_source("UPSTREAM_TEST", err).into_response();
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "UPSTREAM_TEST");
+        assert_eq!(body["httpStatus"], 502);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_passed_on() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/missing")
+            .with_status(404)
+            .with_body("not found")
+            .expect(1)
+            .create();
+        let res = upstream()
+            .get(
+                &Client::new(),
+                Url::parse(&format!("{}/missing", server.url())).unwrap(),
+            )
+            .await
+            .expect("Ok");
+        m.assert();
+        assert_eq!(res.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn open_circuit_fails_fast() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/down")
+            .with_status(500)
+            .expect(3)
+            .create();
+        let upstream = upstream_with(CircuitBreaker::new("test", 1, Duration::from_secs(30)));
+        let url = Url::parse(&format!("{}/down", server.url())).unwrap();
+        upstream
+            .get(&Client::new(), url.clone())
+            .await
+            .expect_err("Err");
+
+        let err = upstream.get(&Client::new(), url).await.expect_err("Err");
+        m.assert();
+        assert!(matches!(err, SourceError::Unavailable(_)), "{err:?}");
+    }
+}
@@ -0,0 +1,9 @@
+use axum::{Json, extract::State};
+
+use super::circuit_breaker::BreakerStatus;
+use crate::app_state::AppState;
+
+/// Circuit breaker state of every upstream.
+pub async fn upstream_status(State(state): State<AppState>) -> Json<Vec<BreakerStatus>> {
+    Json(state.breakers().all().map(|b| b.status()).into())
+}
@@ -0,0 +1,379 @@
+use crate::model::Corpus;
+use crate::upstream::source_error::SourceError;
+use crate::upstream::upstream_proxy::Upstream;
+use crate::util::truncate;
+use axum::http::StatusCode;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.wortschatz-leipzig.de";
+
+/// The corpus list changes a few times a year.
+const CATALOG_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// By base URL: when the corpora were fetched, and the corpora.
+type CachedCorpora = HashMap<String, (Instant, Arc<Vec<Corpus>>)>;
+
+/// Cached list of the corpora Wortschatz Leipzig offers.
+#[derive(Clone, Default)]
+pub struct CorpusCatalog {
+    cached: Arc<Mutex<CachedCorpora>>,
+}
+
+impl CorpusCatalog {
+    /// All corpora at `base_url`, fetched at most once per `CATALOG_TTL`.
+    /// Failed fetches are not cached.
+    pub async fn corpora(
+        &self,
+        http_client: &Client,
+        upstream: &Upstream,
+        base_url: &str,
+    ) -> Result<Arc<Vec<Corpus>>, SourceError> {
+        if let Some((fetched_at, corpora)) = self.cached.lock().unwrap().get(base_url)
+            && fetched_at.elapsed() < CATALOG_TTL
+        {
+            return Ok(corpora.clone());
+        }
+        let url = format!("{base_url}/ws/corpora/availableCorpora");
+        let raw: Vec<RawCorpus> = get_json(http_client, upstream, parse_url(&url)?).await?;
+        let corpora = Arc::new(raw.into_iter().map(RawCorpus::into_corpus).collect());
+        self.cached
+            .lock()
+            .unwrap()
+            .insert(base_url.to_string(), (Instant::now(), Arc::clone(&corpora)));
+        Ok(corpora)
+    }
+
+    /// Corpora of `lang_iso3`, the default one first.
+    pub async fn corpora_of(
+        &self,
+        http_client: &Client,
+        upstream: &Upstream,
+        base_url: &str,
+        lang_iso3: &str,
+    ) -> Result<Vec<Corpus>, SourceError> {
+        let mut corpora: Vec<Corpus> = self
+            .corpora(http_client, upstream, base_url)
+            .await?
+            .iter()
+            .filter(|c| c.lang_iso3 == lang_iso3)
+            .cloned()
+            .collect();
+        corpora.sort_by_key(|c| {
+            (
+                genre_rank(c.genre.as_deref()),
+                std::cmp::Reverse(c.size_sentences),
+                std::cmp::Reverse(c.year),
+            )
+        });
+        Ok(corpora)
+    }
+}
+
+/// News corpora have the most typical example sentences, web corpora the
+/// least edited ones.
+fn genre_rank(genre: Option<&str>) -> u8 {
+    match genre {
+        Some("news") => 0,
+        Some("mixed" | "mixed-typical") => 1,
+        Some("wikipedia") => 2,
+        Some("newscrawl") => 3,
+        Some("web") => 4,
+        _ => 5,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawCorpus {
+    corpus_name: String,
+    language: Option<String>,
+}
+
+impl RawCorpus {
+    /// Corpus names follow `<lang>[-<variant>]_<genre>_<year>_<size>`,
+    /// e.g. "deu_news_2012_1M" or "eng-simple_wikipedia_2016_300K".
+    fn into_corpus(self) -> Corpus {
+        let mut parts = self.corpus_name.split('_');
+        let name_lang = parts.next().unwrap_or_default().to_string();
+        let genre = parts.next().map(str::to_string);
+        let year = parts.next().and_then(|y| y.parse().ok());
+        let size_sentences = parts.next().and_then(parse_size);
+        Corpus {
+            lang_iso3: self
+                .language
+                .filter(|_| !name_lang.contains('-'))
+                .unwrap_or(name_lang),
+            genre,
+            year,
+            size_sentences,
+            name: self.corpus_name,
+        }
+    }
+}
+
+/// "300K" → 300000, "1M" → 1000000
+fn parse_size(size: &str) -> Option<i64> {
+    let (digits, factor) = match size.char_indices().last()? {
+        (i, 'K') => (&size[..i], 1_000),
+        (i, 'M') => (&size[..i], 1_000_000),
+        _ => (size, 1),
+    };
+    digits.parse::<i64>().ok().map(|n| n * factor)
+}
+
+#[derive(Deserialize)]
+struct LeipzigSentencesResponse {
+    #[serde(default)]
+    sentences: Vec<LeipzigSentence>,
+}
+
+#[derive(Deserialize)]
+pub struct LeipzigSentence {
+    pub id: Option<String>,
+    pub sentence: String,
+}
+
+/// Sentences of `corpus` containing `term`; empty if the term is unknown.
+pub async fn sentences(
+    http_client: &Client,
+    upstream: &Upstream,
+    base_url: &str,
+    corpus: &str,
+    term: &str,
+    limit: usize,
+) -> Result<Vec<LeipzigSentence>, SourceError> {
+    let mut url = ws_url(base_url, &["sentences", corpus, "sentences", term.trim()])?;
+    url.query_pairs_mut()
+        .append_pair("limit", &limit.to_string());
+    let res: Option<LeipzigSentencesResponse> =
+        get_json_or_not_found(http_client, upstream, url).await?;
+    Ok(res.map(|r| r.sentences).unwrap_or_default())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeipzigWord {
+    pub freq: Option<i64>,
+    pub word_rank: i64,
+    pub frequency_class: i32,
+}
+
+/// Frequency of `word` in `corpus`; `None` if the word is unknown.
+pub async fn word(
+    http_client: &Client,
+    upstream: &Upstream,
+    base_url: &str,
+    corpus: &str,
+    word: &str,
+) -> Result<Option<LeipzigWord>, SourceError> {
+    let url = ws_url(base_url, &["words", corpus, "word", word.trim()])?;
+    get_json_or_not_found(http_client, upstream, url).await
+}
+
+#[derive(Clone, Copy)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Deserialize)]
+pub struct LeipzigCooccurrence {
+    /// The neighbour
+    pub w2: LeipzigCooccurringWord,
+    pub freq: i64,
+    pub significance: f64,
+}
+
+#[derive(Deserialize)]
+pub struct LeipzigCooccurringWord {
+    pub word: String,
+}
+
+/// Words occurring most significantly right before (`Side::Left`) or
+/// right after `word`, most significant first.
+pub async fn neighbours(
+    http_client: &Client,
+    upstream: &Upstream,
+    base_url: &str,
+    corpus: &str,
+    word: &str,
+    side: Side,
+    limit: usize,
+) -> Result<Vec<LeipzigCooccurrence>, SourceError> {
+    let endpoint = match side {
+        Side::Left => "leftneighbours",
+        Side::Right => "rightneighbours",
+    };
+    let mut url = ws_url(base_url, &["cooccurrences", corpus, endpoint, word.trim()])?;
+    url.query_pairs_mut()
+        .append_pair("limit", &limit.to_string());
+    let res: Option<Vec<LeipzigCooccurrence>> =
+        get_json_or_not_found(http_client, upstream, url).await?;
+    let mut res = res.unwrap_or_default();
+    res.sort_by(|a, b| b.significance.total_cmp(&a.significance));
+    Ok(res)
+}
+
+pub(crate) fn parse_url(url: &str) -> Result<Url, SourceError> {
+    Url::parse(url).map_err(|e| SourceError::Internal(e.to_string()))
+}
+
+/// `{base_url}/ws/{segments}` with every segment percent-encoded.
+pub(crate) fn ws_url(base_url: &str, segments: &[&str]) -> Result<Url, SourceError> {
+    let mut url = parse_url(base_url)?;
+    url.path_segments_mut()
+        .map_err(|_| SourceError::Internal("invalid base URL".to_string()))?
+        .pop_if_empty()
+        .push("ws")
+        .extend(segments);
+    Ok(url)
+}
+
+async fn get_json<T: DeserializeOwned>(
+    http_client: &Client,
+    upstream: &Upstream,
+    url: Url,
+) -> Result<T, SourceError> {
+    get_json_or_not_found(http_client, upstream, url)
+        .await?
+        .ok_or_else(|| {
+            SourceError::Upstream(StatusCode::NOT_FOUND, "Leipzig returned 404".to_string())
+        })
+}
+
+/// `None` on 404, which Leipzig answers for unknown words.
+async fn get_json_or_not_found<T: DeserializeOwned>(
+    http_client: &Client,
+    upstream: &Upstream,
+    url: Url,
+) -> Result<Option<T>, SourceError> {
+    let res = upstream.get(http_client, url).await?;
+    if res.status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !res.status.is_success() {
+        let body = String::from_utf8_lossy(&res.body);
+        error!(status = %res.status, body = %truncate(&body), "Leipzig non-success");
+        return Err(SourceError::Upstream(res.status, body.into_owned()));
+    }
+
+    serde_json::from_slice(&res.body).map(Some).map_err(|e| {
+        error!(error = %e, "failed to deserialize Leipzig response");
+        SourceError::Upstream(StatusCode::BAD_GATEWAY, e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upstream::circuit_breaker::UpstreamBreakers;
+    use crate::wortschatz_leipzig::wortschatz_leipzig_proxy::UPSTREAM;
+    use mockito::Server;
+
+    fn upstream() -> Upstream {
+        Upstream::new(UPSTREAM, UpstreamBreakers::default().leipzig)
+    }
+
+    const CORPORA_JSON: &str = r#"[
+        {"corpusName": "deu_web_2019_1M", "language": "deu"},
+        {"corpusName": "deu_news_2012_300K", "language": "deu"},
+        {"corpusName": "deu_news_2023_1M", "language": "deu"},
+        {"corpusName": "deu-ch_news_2023_1M", "language": "deu"},
+        {"corpusName": "eng_news_2020_1M", "language": "eng"}
+    ]"#;
+
+    #[tokio::test]
+    async fn catalog_is_cached_and_sorted_by_preference() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/ws/corpora/availableCorpora")
+            .with_body(CORPORA_JSON)
+            .expect(1)
+            .create();
+
+        let catalog = CorpusCatalog::default();
+        let client = Client::new();
+        let upstream = upstream();
+        let deu = catalog
+            .corpora_of(&client, &upstream, &server.url(), "deu")
+            .await
+            .expect("Ok");
+        assert_eq!(
+            deu.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            vec!["deu_news_2023_1M", "deu_news_2012_300K", "deu_web_2019_1M"]
+        );
+        assert_eq!(deu[0].year, Some(2023));
+        assert_eq!(deu[1].size_sentences, Some(300_000));
+
+        let eng = catalog
+            .corpora_of(&client, &upstream, &server.url(), "eng")
+            .await
+            .expect("Ok");
+        assert_eq!(eng.len(), 1);
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn catalog_is_cached_per_base_url() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/ws/corpora/availableCorpora")
+            .with_body(CORPORA_JSON)
+            .expect(1)
+            .create();
+        let mut other = Server::new_async().await;
+        let other_m = other
+            .mock("GET", "/ws/corpora/availableCorpora")
+            .with_body(r#"[{"corpusName": "fra_news_2020_1M", "language": "fra"}]"#)
+            .expect(1)
+            .create();
+
+        let catalog = CorpusCatalog::default();
+        let client = Client::new();
+        let upstream = upstream();
+        assert_eq!(
+            catalog
+                .corpora(&client, &upstream, &server.url())
+                .await
+                .expect("Ok")
+                .len(),
+            5
+        );
+        let corpora = catalog
+            .corpora(&client, &upstream, &other.url())
+            .await
+            .expect("Ok");
+        assert_eq!(corpora[0].name, "fra_news_2020_1M");
+        m.assert();
+        other_m.assert();
+    }
+
+    #[tokio::test]
+    async fn terms_are_path_encoded() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/ws/sentences/deu_news_2023_1M/sentences/a%2Fb%3F")
+            .match_query(mockito::Matcher::Any)
+            .with_body(r#"{"sentences": []}"#)
+            .create();
+
+        let sentences = sentences(
+            &Client::new(),
+            &upstream(),
+            &server.url(),
+            "deu_news_2023_1M",
+            "a/b?",
+            10,
+        )
+        .await
+        .expect("Ok");
+        assert!(sentences.is_empty());
+        m.assert();
+    }
+}
@@ -0,0 +1,402 @@
+use super::leipzig_client::{self, CorpusCatalog, DEFAULT_BASE_URL, LeipzigCooccurrence, Side};
+use crate::model::lexical_item_detail::{Collocate, Collocations, Example, Frequency};
+use crate::model::{LexicalItemDetail, Sentence, TranslationsSet};
+use crate::upstream::source_error::SourceError;
+use crate::upstream::upstream_proxy::Upstream;
+use reqwest::Client;
+use tracing::warn;
+
+const DEFAULT_LIMIT: usize = 10;
+
+/// Looks `query` up in `corpus` (by default the preferred corpus of
+/// `lang_iso3`): its frequency, its most significant left and right
+/// neighbours, and sentences containing it as (untranslated) examples.
+/// Leipzig is asked through `upstream`.
+pub async fn get(
+    http_client: &Client,
+    upstream: &Upstream,
+    catalog: &CorpusCatalog,
+    query: &str,
+    lang_iso3: &str,
+    corpus: Option<&str>,
+    base_url: Option<&str>,
+) -> Result<Vec<LexicalItemDetail>, SourceError> {
+    let base_url = base_url.unwrap_or(DEFAULT_BASE_URL);
+    let Some(corpus) =
+        resolve_corpus(http_client, upstream, catalog, base_url, lang_iso3, corpus).await?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let (word, left, right, sentences) = tokio::join!(
+        leipzig_client::word(http_client, upstream, base_url, &corpus, query),
+        leipzig_client::neighbours(
+            http_client,
+            upstream,
+            base_url,
+            &corpus,
+            query,
+            Side::Left,
+            DEFAULT_LIMIT
+        ),
+        leipzig_client::neighbours(
+            http_client,
+            upstream,
+            base_url,
+            &corpus,
+            query,
+            Side::Right,
+            DEFAULT_LIMIT
+        ),
+        leipzig_client::sentences(
+            http_client,
+            upstream,
+            base_url,
+            &corpus,
+            query,
+            DEFAULT_LIMIT
+        ),
+    );
+    // Frequency and collocations are extras: without them the sentences are
+    // still worth returning.
+    let word = best_effort(word, "frequency");
+    let left = best_effort(left, "left neighbours");
+    let right = best_effort(right, "right neighbours");
+    let sentences = sentences?;
+
+    let source = "leipzig".to_string();
+    let mut out = Vec::new();
+    if let Some(word) = word {
+        out.push(LexicalItemDetail::Frequency(Frequency {
+            frequency_class: word.frequency_class,
+            rank: word.word_rank,
+            count: word.freq,
+            corpus: corpus.clone(),
+            source: source.clone(),
+        }));
+    }
+    if !left.is_empty() || !right.is_empty() {
+        let collocates = |cooccurrences: Vec<LeipzigCooccurrence>| {
+            cooccurrences
+                .into_iter()
+                .map(|c| Collocate {
+                    word: c.w2.word,
+                    count: c.freq,
+                    significance: c.significance,
+                })
+                .collect()
+        };
+        out.push(LexicalItemDetail::Collocations(Collocations {
+            left: collocates(left),
+            right: collocates(right),
+            corpus: corpus.clone(),
+            source: source.clone(),
+        }));
+    }
+    out.extend(sentences.into_iter().map(|s| {
+        LexicalItemDetail::Example(Example {
+            translations_set: TranslationsSet {
+                original: Sentence {
+                    source_id: s.id,
+                    ..Sentence::new(s.sentence, lang_iso3, &source)
+                },
+                translations: Vec::new(),
+                translations_qualities: None,
+            },
+            source: source.clone(),
+        })
+    }));
+    Ok(out)
+}
+
+fn best_effort<T: Default>(result: Result<T, SourceError>, what: &str) -> T {
+    result.unwrap_or_else(|e| {
+        warn!(error = ?e, "Leipzig {what} unavailable, skipping");
+        T::default()
+    })
+}
+
+/// Checks a requested corpus against the catalog, or picks the default one.
+/// `None` if Leipzig has no corpus of `lang_iso3`.
+pub(crate) async fn resolve_corpus(
+    http_client: &Client,
+    upstream: &Upstream,
+    catalog: &CorpusCatalog,
+    base_url: &str,
+    lang_iso3: &str,
+    corpus: Option<&str>,
+) -> Result<Option<String>, SourceError> {
+    let corpora = catalog
+        .corpora_of(http_client, upstream, base_url, lang_iso3)
+        .await;
+    match (corpus, corpora) {
+        (Some(corpus), Ok(corpora)) => {
+            if corpora.iter().any(|c| c.name == corpus) {
+                Ok(Some(corpus.to_string()))
+            } else {
+                Err(SourceError::BadInput(format!(
+                    "unknown corpus {corpus} for {lang_iso3}"
+                )))
+            }
+        }
+        (Some(_), Err(e)) => Err(e),
+        (None, Ok(corpora)) => Ok(corpora.into_iter().next().map(|c| c.name)),
+        (None, Err(e)) => {
+            let corpus = fallback_corpus(lang_iso3);
+            warn!(error = %e.message(), %corpus, "Leipzig corpus catalog unavailable, guessing the corpus");
+            Ok(Some(corpus))
+        }
+    }
+}
+
+/// Leipzig names its corpora `<iso3>_<genre>_<year>_<size>`; the 2012 news
+/// corpora with 1M sentences exist for most major languages.
+fn fallback_corpus(lang_iso3: &str) -> String {
+    format!("{lang_iso3}_news_2012_1M")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upstream::circuit_breaker::UpstreamBreakers;
+    use crate::wortschatz_leipzig::wortschatz_leipzig_proxy::UPSTREAM;
+    use axum::http::StatusCode;
+    use mockito::{Matcher, Server};
+
+    fn upstream() -> Upstream {
+        Upstream::new(UPSTREAM, UpstreamBreakers::default().leipzig)
+    }
+
+    #[tokio::test]
+    async fn sentences_become_examples() {
+        let mut server = Server::new_async().await;
+        let _catalog = server
+            .mock("GET", "/ws/corpora/availableCorpora")
+            .with_body(r#"[{"corpusName": "deu_news_2012_1M", "language": "deu"}]"#)
+            .create();
+        let _m = server
+            .mock("GET", "/ws/sentences/deu_news_2012_1M/sentences/Hund")
+            .match_query(Matcher::UrlEncoded("limit".into(), "10".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"count": 1, "sentences": [{"id": "a1", "sentence": "Der Hund schläft."}]}"#,
+            )
+            .create();
+
+        let _word = server
+            .mock("GET", "/ws/words/deu_news_2012_1M/word/Hund")
+            .with_status(404)
+            .create();
+        let _neighbours = server
+            .mock("GET", Matcher::Regex("^/ws/cooccurrences/".into()))
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .create();
+
+        let catalog = CorpusCatalog::default();
+        let items = get(
+            &Client::new(),
+            &upstream(),
+            &catalog,
+            "Hund",
+            "deu",
+            None,
+            Some(&server.url()),
+        )
+        .await
+        .expect("Ok");
+
+        let source = "leipzig".to_string();
+        assert_eq!(
+            items,
+            vec![LexicalItemDetail::Example(Example {
+                translations_set: TranslationsSet {
+                    original: Sentence {
+                        source_id: Some("a1".into()),
+                        ..Sentence::new("Der Hund schläft.", "deu", &source)
+                    },
+                    translations: vec![],
+                    translations_qualities: None,
+                },
+                source: source.clone(),
+            })]
+        );
+    }
+
+    #[tokio::test]
+    async fn frequency_and_collocations_come_first() {
+        let mut server = Server::new_async().await;
+        let _catalog = server
+            .mock("GET", "/ws/corpora/availableCorpora")
+            .with_body(r#"[{"corpusName": "deu_news_2012_1M", "language": "deu"}]"#)
+            .create();
+        let _word = server
+            .mock("GET", "/ws/words/deu_news_2012_1M/word/Hund")
+            .with_body(r#"{"id": 7, "word": "Hund", "freq": 8201, "wordRank": 2312, "frequencyClass": 10}"#)
+            .create();
+        let _left = server
+            .mock("GET", "/ws/cooccurrences/deu_news_2012_1M/leftneighbours/Hund")
+            .match_query(Matcher::UrlEncoded("limit".into(), "10".into()))
+            .with_body(
+                r#"[
+                    {"w1": {"word": "Hund"}, "w2": {"word": "kleiner"}, "freq": 12, "significance": 40.5},
+                    {"w1": {"word": "Hund"}, "w2": {"word": "der"}, "freq": 900, "significance": 310.2}
+                ]"#,
+            )
+            .create();
+        let _right = server
+            .mock(
+                "GET",
+                "/ws/cooccurrences/deu_news_2012_1M/rightneighbours/Hund",
+            )
+            .match_query(Matcher::Any)
+            .with_body("[]")
+            .create();
+        let _sentences = server
+            .mock("GET", "/ws/sentences/deu_news_2012_1M/sentences/Hund")
+            .match_query(Matcher::Any)
+            .with_body(r#"{"sentences": []}"#)
+            .create();
+
+        let catalog = CorpusCatalog::default();
+        let items = get(
+            &Client::new(),
+            &upstream(),
+            &catalog,
+            "Hund",
+            "deu",
+            None,
+            Some(&server.url()),
+        )
+        .await
+        .expect("Ok");
+
+        let corpus = "deu_news_2012_1M".to_string();
+        let source = "leipzig".to_string();
+        assert_eq!(
+            items,
+            vec![
+                LexicalItemDetail::Frequency(Frequency {
+                    frequency_class: 10,
+                    rank: 2312,
+                    count: Some(8201),
+                    corpus: corpus.clone(),
+                    source: source.clone(),
+                }),
+                LexicalItemDetail::Collocations(Collocations {
+                    left: vec![
+                        Collocate {
+                            word: "der".into(),
+                            count: 900,
+                            significance: 310.2,
+                        },
+                        Collocate {
+                            word: "kleiner".into(),
+                            count: 12,
+                            significance: 40.5,
+                        },
+                    ],
+                    right: vec![],
+                    corpus,
+                    source,
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sentences_are_kept_when_frequency_and_collocations_fail() {
+        let mut server = Server::new_async().await;
+        let _catalog = server
+            .mock("GET", "/ws/corpora/availableCorpora")
+            .with_body(r#"[{"corpusName": "deu_news_2012_1M", "language": "deu"}]"#)
+            .create();
+        let _word = server
+            .mock("GET", "/ws/words/deu_news_2012_1M/word/Hund")
+            .with_status(500)
+            .create();
+        let _neighbours = server
+            .mock("GET", Matcher::Regex("^/ws/cooccurrences/".into()))
+            .match_query(Matcher::Any)
+            .with_status(503)
+            .create();
+        let _sentences = server
+            .mock("GET", "/ws/sentences/deu_news_2012_1M/sentences/Hund")
+            .match_query(Matcher::Any)
+            .with_body(r#"{"sentences": [{"id": "a1", "sentence": "Der Hund schläft."}]}"#)
+            .create();
+
+        let catalog = CorpusCatalog::default();
+        let items = get(
+            &Client::new(),
+            &upstream(),
+            &catalog,
+            "Hund",
+            "deu",
+            None,
+            Some(&server.url()),
+        )
+        .await
+        .expect("Ok");
+        assert_eq!(items.len(), 1);
+        assert!(matches!(items[0], LexicalItemDetail::Example(_)));
+    }
+
+    #[tokio::test]
+    async fn unknown_corpus_is_bad_request() {
+        let mut server = Server::new_async().await;
+        let _catalog = server
+            .mock("GET", "/ws/corpora/availableCorpora")
+            .with_body(r#"[{"corpusName": "deu_news_2012_1M", "language": "deu"}]"#)
+            .create();
+
+        let catalog = CorpusCatalog::default();
+        let err = get(
+            &Client::new(),
+            &upstream(),
+            &catalog,
+            "Hund",
+            "deu",
+            Some("eng_news_2012_1M"),
+            Some(&server.url()),
+        )
+        .await
+        .expect_err("Err");
+        assert!(matches!(err, SourceError::BadInput(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn upstream_bad_requests_are_not_unknown_corpora() {
+        let mut server = Server::new_async().await;
+        let _catalog = server
+            .mock("GET", "/ws/corpora/availableCorpora")
+            .with_body(r#"[{"corpusName": "deu_news_2012_1M", "language": "deu"}]"#)
+            .create();
+        let _m = server
+            .mock(
+                "GET",
+                Matcher::Regex("^/ws/(words|cooccurrences|sentences)/".into()),
+            )
+            .match_query(Matcher::Any)
+            .with_status(400)
+            .create();
+
+        let catalog = CorpusCatalog::default();
+        let err = get(
+            &Client::new(),
+            &upstream(),
+            &catalog,
+            "Hund",
+            "deu",
+            Some("deu_news_2012_1M"),
+            Some(&server.url()),
+        )
+        .await
+        .expect_err("Err");
+        assert!(
+            matches!(err, SourceError::Upstream(StatusCode::BAD_REQUEST, _)),
+            "{err:?}"
+        );
+        assert_eq!(err.code(UPSTREAM.error_code), "UPSTREAM_LEIPZIG");
+    }
+}
@@ -1 +1,3 @@
+pub(crate) mod leipzig_client;
+pub(crate) mod leipzig_lexical_items;
 pub mod wortschatz_leipzig_proxy;
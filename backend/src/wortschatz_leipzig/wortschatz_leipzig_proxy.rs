@@ -1,12 +1,27 @@
 use axum::{
     body::Body,
     extract::{Path, Query, State},
-    http::{header, Response, StatusCode},
+    http::Response,
+    response::IntoResponse,
 };
 use serde::Deserialize;
-use tracing::error;
+use std::time::Duration;
 
+use super::leipzig_client::{DEFAULT_BASE_URL, ws_url};
+use super::leipzig_lexical_items::resolve_corpus;
 use crate::app_state::AppState;
+use crate::upstream::{proxy_error::ProxyError, upstream_proxy::UpstreamConfig};
+
+/// Corpora are fixed once published.
+pub const UPSTREAM: UpstreamConfig = UpstreamConfig {
+    name: "Wortschatz Leipzig",
+    error_code: "UPSTREAM_LEIPZIG",
+    timeout: Duration::from_secs(10),
+    retries: 2,
+    backoff: Duration::from_millis(200),
+    default_max_age: Duration::from_secs(24 * 60 * 60),
+    cache_bytes: 32 * 1024 * 1024,
+};
 
 #[derive(Deserialize)]
 pub struct LeipzigQueryParams {
@@ -22,70 +37,47 @@ pub async fn wortschatz_leipzig_proxy(
     State(state): State<AppState>,
     Path((corpus, term)): Path<(String, String)>,
     Query(params): Query<LeipzigQueryParams>,
-) -> Result<Response<Body>, (StatusCode, String)> {
+) -> Result<Response<Body>, ProxyError> {
     let corpus = corpus.trim();
     let term = term.trim();
 
     if corpus.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "corpus must not be empty".into(),
-        ));
+        return Err(ProxyError::bad_request("corpus must not be empty"));
     }
 
     if term.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "term must not be empty".into(),
-        ));
+        return Err(ProxyError::bad_request("term must not be empty"));
     }
 
-    let url = format!(
-        "https://api.wortschatz-leipzig.de/ws/sentences/{corpus}/sentences/{term}"
-    );
-
-    let mut req = state.http_client().get(&url);
+    // Only corpora from the catalog are forwarded, so `corpus` cannot
+    // change the upstream path.
+    let lang_iso3 = corpus.split(['_', '-']).next().unwrap_or_default();
+    resolve_corpus(
+        state.http_client(),
+        state.leipzig_upstream(),
+        state.leipzig_corpora(),
+        DEFAULT_BASE_URL,
+        lang_iso3,
+        Some(corpus),
+    )
+    .await
+    .map_err(|e| ProxyError::from_source(UPSTREAM.error_code, e))?;
 
+    let mut url = ws_url(DEFAULT_BASE_URL, &["sentences", corpus, "sentences", term])
+        .map_err(|e| ProxyError::from_source(UPSTREAM.error_code, e))?;
     if let Some(offset) = params.offset {
-        req = req.query(&[("offset", offset)]);
+        url.query_pairs_mut()
+            .append_pair("offset", &offset.to_string());
     }
     if let Some(limit) = params.limit {
-        req = req.query(&[("limit", limit)]);
+        url.query_pairs_mut()
+            .append_pair("limit", &limit.to_string());
     }
 
-    let upstream = match req.send().await {
-        Ok(r) => r,
-        Err(e) => {
-            error!(error = %e, %url, "network error talking to Leipzig");
-            return Err((
-                StatusCode::BAD_GATEWAY,
-                "Wortschatz Leipzig upstream error".into(),
-            ));
-        }
-    };
-
-    let status = upstream.status();
-    let headers = upstream.headers().clone();
-
-    let body_bytes = match upstream.bytes().await {
-        Ok(b) => b,
-        Err(e) => {
-            error!(error = %e, "failed to read Leipzig response body");
-            return Err((
-                StatusCode::BAD_GATEWAY,
-                "Wortschatz Leipzig upstream error".into(),
-            ));
-        }
-    };
-
-    let mut builder = Response::builder().status(status);
-    if let Some(ct) = headers.get(header::CONTENT_TYPE) {
-        builder = builder.header(header::CONTENT_TYPE, ct);
-    }
-
-    let resp = builder
-        .body(Body::from(body_bytes))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    Ok(resp)
+    let upstream = state
+        .leipzig_upstream()
+        .get(state.http_client(), url)
+        .await
+        .map_err(|e| ProxyError::from_source(UPSTREAM.error_code, e))?;
+    Ok(upstream.into_response())
 }